@@ -1,5 +1,10 @@
 include!("src/cli.rs");
 
+// The CLI reads its default values from the constants
+#[allow(dead_code)]
+#[path = "src/constants.rs"]
+mod constants;
+
 use std::fs;
 use std::path::PathBuf;
 
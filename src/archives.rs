@@ -0,0 +1,203 @@
+use crate::commands::{hash, plural};
+use crate::constants::ARCHIVES_DIR_NAME;
+use crate::felloe::Felloe;
+use indicatif::HumanBytes;
+use log::*;
+use std::{fs, path::PathBuf};
+
+/// A verified release archive kept in the download cache.
+#[derive(Debug)]
+pub struct StoredArchive {
+    pub sha256: String,
+    pub file_name: String,
+    pub size: u64,
+    pub path: PathBuf,
+}
+
+impl StoredArchive {
+    /// The helm version in the archive name, e.g. `v3.2.4` for `helm-v3.2.4-linux-amd64.tar.gz`.
+    pub fn version(&self) -> Option<&str> {
+        let name = self
+            .file_name
+            .strip_prefix("helm-")?
+            .strip_suffix(".tar.gz")?;
+
+        // Versions may contain dashes themselves, the platform never does
+        name.rsplitn(3, '-').nth(2)
+    }
+}
+
+/// Where verified archives are kept, one directory per sha256 digest. Several felloe homes can
+/// share it by pointing `Felloe::with_downloads_dir` or the `downloads.path` setting at the same
+/// place.
+pub fn get_archives_path(felloe: &Felloe) -> Result<PathBuf, failure::Error> {
+    if let Some(path) = felloe.downloads_dir() {
+        return Ok(path.to_path_buf());
+    }
+
+    Ok(felloe
+        .config()?
+        .downloads
+        .path
+        .unwrap_or_else(|| felloe.home().join(ARCHIVES_DIR_NAME)))
+}
+
+/// Check that `sha` is a sha256 digest: exactly 64 lowercase hex characters. Digests name
+/// directories in the download cache, so anything else could point outside it.
+pub fn validate_digest(sha: &str) -> Result<(), failure::Error> {
+    let valid = sha.len() == 64
+        && sha
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+
+    if !valid {
+        return Err(failure::err_msg(format!(
+            "Invalid sha256 digest {:?}. Expected 64 lowercase hex characters",
+            sha
+        )));
+    }
+
+    Ok(())
+}
+
+/// The archive with digest `sha`, if it is stored and still matches its digest.
+pub fn find(felloe: &Felloe, sha: &str) -> Option<Vec<u8>> {
+    if let Err(e) = validate_digest(sha) {
+        warn!("{}", e);
+        return None;
+    }
+
+    let dir = get_archives_path(felloe).ok()?.join(sha);
+    let path = dir
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| !entry.file_name().to_string_lossy().starts_with('.'))?
+        .path();
+    let file = fs::read(&path).ok()?;
+
+    if hash(&file).ok()? != sha {
+        warn!(
+            "Removing corrupt archive {:?} from the download cache",
+            path
+        );
+        fs::remove_file(&path).ok();
+        fs::remove_dir(&dir).ok();
+        return None;
+    }
+
+    debug!("Reusing {:?} from the download cache", path);
+    Some(file)
+}
+
+/// Keep a verified archive under its digest.
+pub fn store(
+    felloe: &Felloe,
+    sha: &str,
+    file_name: &str,
+    file: &[u8],
+) -> Result<(), failure::Error> {
+    validate_digest(sha)?;
+
+    let dir = get_archives_path(felloe)?.join(sha);
+    let path = dir.join(file_name);
+
+    if path.exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)?;
+
+    // Write under a temporary name so another felloe sharing the cache never reads half a file
+    let partial = dir.join(format!(".{}.partial", file_name));
+    fs::write(&partial, file)?;
+    fs::rename(&partial, &path)?;
+
+    debug!("Stored {:?} in the download cache", path);
+    Ok(())
+}
+
+/// Every archive in the download cache.
+pub fn stored(felloe: &Felloe) -> Result<Vec<StoredArchive>, failure::Error> {
+    let root = get_archives_path(felloe)?;
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut archives = Vec::new();
+    for entry in root.read_dir()? {
+        let entry = entry?;
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        for file in entry.path().read_dir()? {
+            let file = file?;
+            let file_name = file.file_name().to_string_lossy().into_owned();
+
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            archives.push(StoredArchive {
+                sha256: entry.file_name().to_string_lossy().into_owned(),
+                size: file.metadata()?.len(),
+                path: file.path(),
+                file_name,
+            });
+        }
+    }
+
+    archives.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(archives)
+}
+
+pub fn ls(felloe: &Felloe) -> Result<(), failure::Error> {
+    let archives = stored(felloe)?;
+
+    for archive in &archives {
+        println!(
+            "{:<40} {:>10}  {}",
+            archive.file_name,
+            HumanBytes(archive.size).to_string(),
+            &archive.sha256[..12.min(archive.sha256.len())]
+        );
+    }
+
+    println!(
+        "{} in {}, {}",
+        plural(archives.len(), "archive"),
+        get_archives_path(felloe)?.display(),
+        HumanBytes(archives.iter().map(|a| a.size).sum())
+    );
+    Ok(())
+}
+
+/// Remove archives from the download cache, or only those of versions no longer installed.
+pub fn clean(felloe: &Felloe, uninstalled: bool) -> Result<(), failure::Error> {
+    let installed = felloe.installed_versions().unwrap_or_default();
+    let mut removed = 0;
+    let mut reclaimed = 0;
+
+    for archive in stored(felloe)? {
+        let is_installed = archive
+            .version()
+            .is_some_and(|v| installed.iter().any(|i| i == v));
+
+        if uninstalled && is_installed {
+            continue;
+        }
+
+        info!("Removing {:?}", archive.path);
+        fs::remove_dir_all(archive.path.parent().unwrap())?;
+        removed += 1;
+        reclaimed += archive.size;
+    }
+
+    println!(
+        "Removed {} and reclaimed {}",
+        plural(removed, "archive"),
+        HumanBytes(reclaimed)
+    );
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use log::Level;
 use structopt::{
-    clap::{AppSettings::ColoredHelp, Shell},
+    clap::{
+        AppSettings::{ColoredHelp, TrailingVarArg},
+        Shell,
+    },
     StructOpt,
 };
 
@@ -12,6 +15,18 @@ pub struct Cli {
     #[structopt(long)]
     pub log_level: Option<Level>,
 
+    #[structopt(
+        long,
+        global = true,
+        possible_values(&["fancy", "plain", "none"])
+    )]
+    /// How to display download progress (default: fancy on a terminal, plain otherwise)
+    pub progress: Option<String>,
+
+    #[structopt(short, long, global = true)]
+    /// Do not display download progress
+    pub quiet: bool,
+
     #[structopt(subcommand)]
     pub cmd: Option<Command>,
 
@@ -22,23 +37,141 @@ pub struct Cli {
     pub latest: bool,
 }
 
+#[derive(Debug, StructOpt)]
+pub struct PlatformOpts {
+    #[structopt(long = "os", possible_values(&["darwin", "linux", "windows"]))]
+    /// Operating system to fetch binaries for (default: the host OS)
+    pub os: Option<String>,
+
+    #[structopt(
+        long = "arch",
+        possible_values(&["386", "amd64", "arm", "arm64", "ppc64le", "s390x"])
+    )]
+    /// Architecture to fetch binaries for (default: the host architecture)
+    pub arch: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ConfirmOpts {
+    #[structopt(short = "y", long = "yes")]
+    /// Remove without asking for confirmation
+    pub yes: bool,
+
+    #[structopt(long = "dry-run")]
+    /// List the versions that would be removed and the space reclaimed
+    pub dry_run: bool,
+}
+
 #[derive(Debug, StructOpt)]
 pub enum Command {
+    #[structopt(name = "homes")]
+    /// Manage isolated helm configuration, data and cache directories
+    Homes {
+        #[structopt(subcommand)]
+        cmd: HomesCommand,
+    },
+
+    #[structopt(name = "install")]
+    /// Install helm <version>, activating it when it targets the host platform
+    Install {
+        version: String,
+
+        #[structopt(flatten)]
+        platform: PlatformOpts,
+    },
+
+    #[structopt(name = "download", alias = "fetch")]
+    /// Download and verify helm <versions> into the cache without activating them
+    Download {
+        #[structopt(required = true)]
+        versions: Vec<String>,
+
+        #[structopt(flatten)]
+        platform: PlatformOpts,
+
+        #[structopt(short = "j", long = "jobs", default_value = "4")]
+        /// Maximum number of concurrent downloads
+        jobs: usize,
+    },
+
     #[structopt(name = "latest")]
     /// Install the latest official helm release
     Latest,
 
+    #[structopt(name = "lock")]
+    /// Write felloe.lock pinning the project's helm version, digests and plugins
+    Lock {
+        #[structopt(long = "platform")]
+        /// Also lock digests for the given platform(s), as <os>-<arch> (e.g. darwin-amd64)
+        platforms: Vec<String>,
+    },
+
+    #[structopt(name = "sync")]
+    /// Install exactly the helm version and plugins pinned in felloe.lock
+    Sync {
+        #[structopt(long = "all-platforms")]
+        /// Download every locked platform, not only the host
+        all_platforms: bool,
+
+        #[structopt(short = "j", long = "jobs", default_value = "4")]
+        /// Maximum number of concurrent downloads
+        jobs: usize,
+    },
+
+    #[structopt(name = "repos")]
+    /// Manage chart repositories across versions
+    Repos {
+        #[structopt(subcommand)]
+        cmd: ReposCommand,
+    },
+
     #[structopt(name = "run")]
     /// Execute downloaded helm <version> with [args ...]
     Run { version: String, args: Vec<String> },
 
     #[structopt(name = "which")]
     /// Output path for downloaded helm <version>
-    Which { version: Option<String> },
+    Which {
+        version: Option<String>,
+
+        #[structopt(flatten)]
+        platform: PlatformOpts,
+    },
 
-    #[structopt(name = "exec")]
+    #[structopt(name = "exec", setting(TrailingVarArg))]
     /// Execute command with modified PATH, so downloaded helm <version> first
-    Exec { version: String, args: Vec<String> },
+    Exec {
+        version: String,
+
+        /// The command to execute followed by its arguments
+        #[structopt(required = true)]
+        args: Vec<String>,
+    },
+
+    #[structopt(name = "shell")]
+    /// Output commands that use helm <version> in the current shell only
+    Shell {
+        version: Option<String>,
+
+        #[structopt(long = "shell")]
+        /// Shell to output commands for (default: detected from $SHELL)
+        shell: Option<Shell>,
+
+        #[structopt(long = "auto")]
+        /// Apply the nearest .helm-version file without replacing a manual override
+        auto: bool,
+
+        #[structopt(long = "unset", conflicts_with = "version")]
+        /// Remove the shell override
+        unset: bool,
+    },
+
+    #[structopt(name = "env")]
+    /// Output a shell hook that applies .helm-version files on directory change
+    Env {
+        /// valid values: bash, fish, zsh, powershell, elvish
+        shell: Option<Shell>,
+    },
 
     #[structopt(name = "remove")]
     /// Remove the given installed version(s)
@@ -47,15 +180,102 @@ pub enum Command {
 
         #[structopt(short = "f", long = "force")]
         force: bool,
+
+        #[structopt(flatten)]
+        confirm: ConfirmOpts,
+    },
+
+    #[structopt(name = "restore")]
+    /// Move helm <version> back from the trash, or list the trash
+    Restore { version: Option<String> },
+
+    #[structopt(name = "migrate")]
+    /// Migrate configuration and releases from helm <v2> to helm <v3> with helm-2to3
+    Migrate {
+        v2: String,
+
+        v3: String,
+
+        #[structopt(long = "dry-run")]
+        /// Simulate each step without changing anything
+        dry_run: bool,
+
+        #[structopt(long = "release")]
+        /// Convert only the given release(s) instead of every helm 2 release
+        releases: Vec<String>,
+
+        #[structopt(long = "skip-cleanup")]
+        /// Keep helm 2 configuration and release data after converting
+        skip_cleanup: bool,
+
+        #[structopt(short = "y", long = "yes")]
+        /// Run every step without asking for confirmation
+        yes: bool,
+    },
+
+    #[structopt(name = "outdated")]
+    /// Show the newest patch, minor and major release for every downloaded version
+    Outdated {
+        #[structopt(long = "refresh-index", hidden = true)]
+        refresh_index: bool,
+    },
+
+    #[structopt(name = "plugins")]
+    /// Manage helm plugins across versions
+    Plugins {
+        #[structopt(subcommand)]
+        cmd: PluginsCommand,
     },
 
     #[structopt(name = "prune")]
-    /// Remove all downloaded versions except the currently installed version
-    Prune,
+    /// Remove downloaded versions except the active version and those kept by a retention policy
+    Prune {
+        #[structopt(long = "keep-latest")]
+        /// Keep the N newest versions
+        keep_latest: Option<usize>,
+
+        #[structopt(long = "keep-per-minor")]
+        /// Keep the N newest patch versions of each minor version
+        keep_per_minor: Option<usize>,
+
+        #[structopt(long = "older-than")]
+        /// Only remove versions downloaded longer ago than this, e.g. 90d, 12h or 2w
+        older_than: Option<String>,
+
+        #[structopt(long = "unused-for")]
+        /// Only remove versions not activated or run for this long, e.g. 60d
+        unused_for: Option<String>,
+
+        #[structopt(long = "keep")]
+        /// Keep the given version(s)
+        keep: Vec<String>,
+
+        #[structopt(flatten)]
+        confirm: ConfirmOpts,
+    },
 
     #[structopt(name = "list")]
     /// Output downloaded versions
-    List,
+    List {
+        #[structopt(short = "l", long = "long")]
+        /// Show when each version was downloaded and last used, and its platforms
+        long: bool,
+    },
+
+    #[structopt(name = "cache")]
+    /// Manage verified release archives kept for reinstalling without downloading
+    Cache {
+        #[structopt(subcommand)]
+        cmd: CacheCommand,
+    },
+
+    #[structopt(name = "du")]
+    /// Show the disk space used by downloaded versions
+    Du {
+        #[structopt(long = "unused-for", default_value = "90d")]
+        /// Flag versions not used for this long as stale, e.g. 90d, 12h or 2w
+        unused_for: String,
+    },
 
     #[structopt(name = "versions")]
     /// Output matching versions available for download
@@ -73,9 +293,43 @@ pub enum Command {
         last: Option<usize>,
     },
 
+    #[structopt(name = "tiller")]
+    /// Manage a local tiller for helm 2 versions
+    Tiller {
+        #[structopt(subcommand)]
+        cmd: TillerCommand,
+    },
+
+    #[structopt(name = "upgrade")]
+    /// Install and activate the newest release in the active version's line
+    Upgrade {
+        #[structopt(long = "patch", conflicts_with_all(&["minor", "major"]))]
+        /// Stay on the active minor version (default)
+        patch: bool,
+
+        #[structopt(long = "minor", conflicts_with = "major")]
+        /// Stay on the active major version
+        minor: bool,
+
+        #[structopt(long = "major")]
+        /// Move to the newest release, whatever its major version
+        major: bool,
+
+        #[structopt(long = "dry-run")]
+        /// Print the upgrade plan without installing anything
+        dry_run: bool,
+
+        #[structopt(long = "prune")]
+        /// Remove the superseded version after upgrading
+        prune: bool,
+    },
+
     #[structopt(name = "uninstall")]
     /// Remove the installed helm
-    Uninstall,
+    Uninstall {
+        #[structopt(flatten)]
+        confirm: ConfirmOpts,
+    },
 
     #[structopt(name = "completions")]
     /// Generate completions for desired shell
@@ -84,3 +338,133 @@ pub enum Command {
         shell: Shell,
     },
 }
+
+#[derive(Debug, StructOpt)]
+pub enum CacheCommand {
+    #[structopt(name = "ls")]
+    /// List the archives in the download cache
+    Ls,
+
+    #[structopt(name = "clean")]
+    /// Remove archives from the download cache
+    Clean {
+        #[structopt(long = "uninstalled")]
+        /// Only remove archives of versions that are not installed
+        uninstalled: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum HomesCommand {
+    #[structopt(name = "path")]
+    /// Output the isolated home for helm <version>
+    Path { version: Option<String> },
+
+    #[structopt(name = "copy")]
+    /// Copy repositories and plugins from helm <from> to helm <to>
+    Copy {
+        from: String,
+
+        to: String,
+
+        #[structopt(long = "repositories")]
+        /// Copy only repositories
+        repositories: bool,
+
+        #[structopt(long = "plugins")]
+        /// Copy only plugins
+        plugins: bool,
+
+        #[structopt(short = "f", long = "force")]
+        /// Overwrite repositories and plugins that already exist
+        force: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum PluginsCommand {
+    #[structopt(name = "list")]
+    /// Output the plugins installed for helm <version>
+    List { version: Option<String> },
+
+    #[structopt(name = "install")]
+    /// Install the plugins listed in the config file for helm <version>
+    Install { version: Option<String> },
+
+    #[structopt(name = "sync")]
+    /// Install the plugins of helm <from> for helm <to>
+    Sync { from: String, to: String },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ReposCommand {
+    #[structopt(name = "list")]
+    /// Output the chart repositories configured for helm <version>
+    List { version: Option<String> },
+
+    #[structopt(name = "sync")]
+    /// Add the chart repositories of helm <from> to helm <to>
+    Sync {
+        from: String,
+
+        to: String,
+
+        #[structopt(long = "update")]
+        /// Run `helm repo update` afterwards
+        update: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum TillerCommand {
+    #[structopt(name = "start")]
+    /// Start a local tiller from the downloaded helm <version>
+    Start {
+        version: Option<String>,
+
+        #[structopt(long = "listen", default_value = "127.0.0.1:44134")]
+        /// Address for tiller to listen on
+        listen: String,
+
+        #[structopt(
+            long = "storage",
+            default_value = "secret",
+            possible_values(&["configmap", "secret", "memory"])
+        )]
+        /// Storage driver for release information
+        storage: String,
+
+        #[structopt(long = "tiller-namespace", default_value = "kube-system")]
+        /// Namespace tiller stores release information in
+        namespace: String,
+    },
+
+    #[structopt(name = "stop")]
+    /// Stop the local tiller
+    Stop { version: Option<String> },
+
+    #[structopt(name = "status")]
+    /// Output the status of the local tiller
+    Status { version: Option<String> },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::DEFAULT_DOWNLOAD_JOBS;
+
+    #[test]
+    fn jobs_default_to_the_shared_constant() {
+        let cli = Cli::from_iter(&["felloe", "download", "v3.2.4"]);
+        match cli.cmd {
+            Some(Command::Download { jobs, .. }) => assert_eq!(jobs, DEFAULT_DOWNLOAD_JOBS),
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        let cli = Cli::from_iter(&["felloe", "sync"]);
+        match cli.cmd {
+            Some(Command::Sync { jobs, .. }) => assert_eq!(jobs, DEFAULT_DOWNLOAD_JOBS),
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+}
@@ -1,103 +1,73 @@
+use crate::archives;
+use crate::config::Isolation;
+use crate::confirm::Confirm;
 use crate::constants::*;
-use crate::progress::DownloadProgress;
-use crate::release::{Release, Releases};
-use crossterm::{
-    cursor::{Hide, MoveTo, Show},
-    input::{input, InputEvent, KeyEvent},
-    queue,
-    screen::{EnterAlternateScreen, LeaveAlternateScreen, RawScreen},
-    style::{Attribute, Color, SetAttribute, SetForegroundColor},
-    terminal::{Clear, ClearType},
-    Output,
-};
+use crate::felloe::Felloe;
+use crate::isolation;
+use crate::metadata;
+use crate::platform::Platform;
+use crate::plugins;
+use crate::progress::{DownloadProgress, Reporter, Stage, Task};
+use crate::project::{find_version_file, normalize_version, read_version_file, remember_project};
+use crate::repos;
+use crate::shell;
+use crate::source;
+use crate::tiller;
 use flate2::read::GzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{HumanBytes, HumanDuration};
 use log::*;
-use reqwest::Client;
+use reqwest::{Client, Response};
 use sha2::{Digest, Sha256};
 use std::{
-    env, fs,
-    io::{copy, stdout, Write},
-    path::PathBuf,
-    process::Command,
-    sync::Arc,
+    collections::VecDeque,
+    env,
+    ffi::OsStr,
+    io::{copy, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Instant,
 };
+use structopt::clap::Shell;
 use tar::Archive;
 
-pub fn fetch_releases(count: usize, include_pre: bool) -> Result<Releases, failure::Error> {
-    let url = format!("{}?per_page={}", &GH_RELEASES_API, &count);
-    let client = Client::new();
-
-    let releases: Releases = client.get(&url).send()?.json()?;
-    let mut releases = Releases(
-        releases
-            .0
-            .into_iter()
-            .filter(|rel| !rel.prerelease || include_pre)
-            .collect(),
-    );
-
-    releases.0.sort_by(|a, b| b.tag_name.cmp(&a.tag_name));
-    releases.0.reverse();
-
-    Ok(releases)
-}
-
-pub fn fetch_release(version: &str) -> Result<Release, failure::Error> {
-    let url = if version == "latest" {
-        format!("{}/{}", &GH_RELEASES_API, version)
-    } else {
-        format!("{}/tags/{}", &GH_RELEASES_API, version)
-    };
-
-    Ok(Client::new().get(&url).send()?.json()?)
-}
-
-pub fn download_release(version: &str) -> Result<(), failure::Error> {
-    let name = format!("helm-{}-{}-{}", version, OS, ARCH);
-    let file_name = format!("{}.tar.gz", name);
-    let file_url = format!("{}/{}", HELM_DOWNLOAD_URL, file_name);
-    let sha_url = format!("{}.sha256", &file_url);
+/// Verify the release archive `file` against `sha`, keep it in the download cache and unpack
+/// it into the version directory `dir`.
+pub(crate) fn unpack_release(
+    felloe: &Felloe,
+    version: &str,
+    platform: &Platform,
+    file: &[u8],
+    sha: &str,
+    dir: &Path,
+    task: &Task,
+) -> Result<(), failure::Error> {
+    let file_name = platform.archive_name(version);
 
-    let file = download(file_url)?;
-    let sha = download(sha_url)?;
+    task.stage(Stage::Verify, &format!("Verifying {}", file_name));
 
-    let verify_spinner = ProgressBar::new_spinner();
-    verify_spinner.enable_steady_tick(150);
-    verify_spinner.set_style(
-        ProgressStyle::default_spinner()
-            .tick_strings(VERIFY_SPINNER_CHARS)
-            .template(VERIFY_SPINNER_TEMPLATE),
-    );
-    verify_spinner.set_message(&format!("Verifying {}", file_name));
+    let hash = hash(file)?;
+    sha256sum(&hash, sha)?;
 
-    let hash = hash(&file)?;
-    let sha = String::from_utf8(sha)?;
-    sha256sum(&hash, &sha)?;
-    verify_spinner.finish_with_message(&format!("helm {} verified", version));
+    if let Err(e) = archives::store(felloe, sha, &file_name, file) {
+        warn!("Unable to keep {} in the download cache: {}", file_name, e);
+    }
 
-    let decoder = GzDecoder::new(&file[..]);
+    let decoder = GzDecoder::new(file);
     let mut archive = Archive::new(decoder);
 
-    let unpack_spinner = ProgressBar::new_spinner();
-    unpack_spinner.enable_steady_tick(120);
-    unpack_spinner.set_style(
-        ProgressStyle::default_spinner()
-            .tick_strings(UNPACK_SPINNER_CHARS)
-            .template(UNPACK_SPINNER_TEMPLATE),
-    );
-    unpack_spinner.set_message(&format!("Unpacking {}", file_name));
-
-    let cache_dir = get_cache_path(version);
+    task.stage(Stage::Unpack, &format!("Unpacking {}", file_name));
 
-    info!("Extracting {} to {:?}", file_name, cache_dir);
-    archive.unpack(cache_dir)?;
+    info!("Extracting {} to {:?}", file_name, dir);
+    archive.unpack(dir)?;
+    metadata::record_downloaded(dir, platform, sha);
 
-    unpack_spinner.finish_with_message(&format!("helm {} installed", version));
+    task.finish(&format!("helm {} installed", version));
     Ok(())
 }
 
-fn hash(file: &[u8]) -> Result<String, failure::Error> {
+pub(crate) fn hash(file: &[u8]) -> Result<String, failure::Error> {
     let mut hasher = Sha256::new();
     hasher.input(file);
     let hash = format!("{:x}", hasher.result());
@@ -115,285 +85,655 @@ fn sha256sum(hash: &str, sum: &str) -> Result<(), failure::Error> {
     }
 }
 
-pub fn download(url: String) -> Result<Vec<u8>, failure::Error> {
-    let file_name = String::from(PathBuf::from(&url).file_name().unwrap().to_str().unwrap());
-
-    debug!("Setting up download progress bar");
-    let length = fetch_content_length(&url).unwrap();
-    let pb = Arc::new(ProgressBar::new(length));
+pub fn download(url: String, task: &Task) -> Result<Vec<u8>, failure::Error> {
+    download_with(&Client::new(), &url, task)
+}
 
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template(BAR_STYLE_TEMPLATE)
-            .progress_chars(BAR_PROGRESS_CHARS),
-    );
+pub(crate) fn download_with(
+    client: &Client,
+    url: &str,
+    task: &Task,
+) -> Result<Vec<u8>, failure::Error> {
+    let file_name = String::from(PathBuf::from(url).file_name().unwrap().to_str().unwrap());
 
-    pb.set_message(&file_name);
+    let length = client.head(url).send()?.content_length().unwrap_or(0);
+    task.start_download(&file_name, length);
 
     info!("Downloading {}", file_name);
+    read_download(client.get(url).send()?.error_for_status()?, task)
+}
+
+/// Read the body of a download started on `task`, reporting its progress.
+pub(crate) fn read_download(response: Response, task: &Task) -> Result<Vec<u8>, failure::Error> {
     let mut stream = DownloadProgress {
-        pb: pb.clone(),
-        stream: Client::new().get(&url).send()?,
+        task,
+        stream: response,
     };
 
     let mut bytes = Vec::<u8>::new();
 
     copy(&mut stream, &mut bytes)?;
-
-    pb.finish();
+    task.finish_download();
 
     Ok(bytes)
 }
 
+/// Size of the archive at `url`, or of the file for local paths.
 pub fn fetch_content_length(url: &str) -> Result<u64, failure::Error> {
-    Ok(Client::new().head(url).send()?.content_length().unwrap())
+    if let Some(path) = source::local_path(url) {
+        return Ok(path.metadata()?.len());
+    }
+
+    let response = Client::new().head(url).send()?;
+    Ok(response.content_length().unwrap_or(0))
 }
 
-pub fn install_latest() -> Result<(), failure::Error> {
+pub fn install_latest(felloe: &Felloe) -> Result<(), failure::Error> {
     info!("Installing latest");
-    let release = fetch_release("latest")?;
-    install(&release.tag_name)
+    install(felloe, "latest", &Platform::current())
 }
 
-pub fn install(version: &str) -> Result<(), failure::Error> {
-    info!("Installing {}", version);
-    let release = fetch_release(version)?;
+/// Install `version` for `platform`, activating it when it targets the host.
+pub fn install(felloe: &Felloe, version: &str, platform: &Platform) -> Result<(), failure::Error> {
+    info!("Installing {} for {}", version, platform);
+    let (version, _) = felloe.install(version, platform)?;
 
-    if !is_helm_installed(version) {
-        info!("Downloading helm {}", version);
-        download_release(&release.tag_name)?;
+    if !platform.is_current() {
+        println!("Downloaded helm {} for {}", version, platform);
+        return Ok(());
     }
 
-    info!("Setting {} as active version", release.tag_name);
-    set_active(version)?;
+    info!("Setting {} as active version", version);
+    let activated = felloe.activate(&version)?;
 
     println!("Activated helm {}", version);
+    after_activate(felloe, activated.previous, &version);
     Ok(())
 }
 
-fn get_cache_path(version: &str) -> PathBuf {
-    dirs::home_dir()
-        .unwrap()
-        .join(&INSTALLATION_DIR)
-        .join("cache")
-        .join(version)
+/// Whether a release had to be downloaded or was already in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fetched {
+    /// Downloaded an archive of the given size in bytes
+    Downloaded(u64),
+    /// Unpacked an archive kept in the download cache
+    Stored,
+    Cached,
 }
 
-fn is_helm_installed(version: &str) -> bool {
-    let cache_dir = get_cache_path(version);
+pub type FetchResult = (String, Result<(String, Fetched), failure::Error>);
+
+/// Fetch `versions` concurrently with at most `jobs` downloads in flight, drawing one progress
+/// line per version. Results are returned in the order the versions were given.
+pub fn fetch_many(
+    felloe: &Felloe,
+    versions: Vec<String>,
+    platform: &Platform,
+    jobs: usize,
+) -> Result<Vec<FetchResult>, failure::Error> {
+    let mut unique: Vec<String> = Vec::new();
+    for version in versions.iter().map(|v| normalize_version(v)) {
+        if !unique.contains(&version) {
+            unique.push(version);
+        }
+    }
 
-    info!("Checking {} for helm binary", cache_dir.to_str().unwrap());
-    if cache_dir.exists() {
-        debug!("Cache directory {} exists", cache_dir.to_str().unwrap());
-        return true;
+    let platform = platform.clone();
+    let items = unique.into_iter().map(|v| (v.clone(), v)).collect();
+
+    let worker = felloe.clone();
+    fetch_parallel(
+        items,
+        jobs,
+        felloe.reporter(),
+        move |version: &String, task| worker.fetch(version, &platform, task),
+    )
+}
+
+/// Run `fetch` over `items` on at most `jobs` threads, one progress line per item. Each item
+/// comes with the label its progress line and result are reported under.
+pub fn fetch_parallel<T, F>(
+    items: Vec<(String, T)>,
+    jobs: usize,
+    reporter: &Reporter,
+    fetch: F,
+) -> Result<Vec<FetchResult>, failure::Error>
+where
+    T: Send + 'static,
+    F: Fn(&T, &Task) -> Result<(String, Fetched), failure::Error> + Send + Sync + 'static,
+{
+    let labels: Vec<String> = items.iter().map(|(label, _)| label.clone()).collect();
+
+    let group = reporter.group();
+    let queue: VecDeque<_> = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, (label, item))| {
+            let task = group.task();
+            task.message(&format!("helm {} waiting", label));
+            (i, label, item, task)
+        })
+        .collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let fetch = Arc::new(fetch);
+    let (tx, rx) = mpsc::channel();
+
+    debug!("Fetching {} versions with {} jobs", labels.len(), jobs);
+    let workers: Vec<_> = (0..jobs.max(1).min(labels.len()))
+        .map(|_| {
+            let queue = queue.clone();
+            let fetch = fetch.clone();
+            let tx = tx.clone();
+
+            thread::spawn(move || loop {
+                let job = queue.lock().unwrap().pop_front();
+                let (i, label, item, task) = match job {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                let result = fetch(&item, &task).map_err(|e| fail_task(&task, &label, e));
+                tx.send((i, result)).ok();
+            })
+        })
+        .collect();
+    drop(tx);
+
+    group.join()?;
+    for worker in workers {
+        worker.join().ok();
     }
 
-    debug!(
-        "Cache directory {} does not exist",
-        cache_dir.to_str().unwrap()
+    let mut results: Vec<Option<_>> = labels.iter().map(|_| None).collect();
+    for (i, result) in rx {
+        results[i] = Some(result);
+    }
+
+    Ok(labels
+        .into_iter()
+        .zip(results)
+        .map(|(label, result)| {
+            let result =
+                result.unwrap_or_else(|| Err(failure::err_msg("download did not complete")));
+            (label, result)
+        })
+        .collect())
+}
+
+/// Print one line per fetched version and a combined total under `summary`. Fails if any
+/// version failed.
+pub fn report_fetched(
+    results: &[FetchResult],
+    summary: &str,
+    started: Instant,
+) -> Result<(), failure::Error> {
+    let width = results
+        .iter()
+        .map(|(label, _)| label.len())
+        .fold(12, usize::max);
+    let mut downloaded = 0;
+    let mut bytes = 0;
+    let mut stored = 0;
+    let mut cached = 0;
+    let mut failed = 0;
+
+    println!();
+    for (version, result) in results {
+        match result {
+            Ok((tag, Fetched::Downloaded(size))) => {
+                downloaded += 1;
+                bytes += size;
+                println!("{:<2$} downloaded {}", tag, HumanBytes(*size), width);
+            }
+            Ok((tag, Fetched::Stored)) => {
+                stored += 1;
+                println!("{:<1$} unpacked from the download cache", tag, width);
+            }
+            Ok((tag, Fetched::Cached)) => {
+                cached += 1;
+                println!("{:<1$} already cached", tag, width);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{:<2$} failed: {}", version, e, width);
+            }
+        }
+    }
+
+    println!(
+        "{}: {} downloaded ({}), {} from the download cache, {} already cached, {} failed in {}",
+        summary,
+        downloaded,
+        HumanBytes(bytes),
+        stored,
+        cached,
+        failed,
+        HumanDuration(started.elapsed())
     );
-    false
+
+    if failed > 0 {
+        return Err(failure::err_msg(format!(
+            "Failed to download {}",
+            plural(failed, "version")
+        )));
+    }
+
+    Ok(())
 }
 
-fn get_bin_path() -> Result<PathBuf, failure::Error> {
-    if cfg!(target_os = "windows") {
-        #[cfg(target_arch = "x86_64")]
-        let path = std::env::var("programfiles(x86)")?;
+pub fn download_versions(
+    felloe: &Felloe,
+    versions: Vec<String>,
+    platform: &Platform,
+    jobs: usize,
+) -> Result<(), failure::Error> {
+    let started = Instant::now();
+    let results = fetch_many(felloe, versions, platform, jobs)?;
 
-        #[cfg(any(target_arch = "x86", target_arch = "i686", target_arch = "i386"))]
-        let path = std::env::var("programfiles")?;
+    let summary = format!("{} for {}", plural(results.len(), "version"), platform);
+    report_fetched(&results, &summary, started)
+}
 
-        Ok(PathBuf::from(path).join("helm"))
+fn fail_task(task: &Task, version: &str, e: failure::Error) -> failure::Error {
+    task.fail(&format!("helm {} failed: {}", version, e));
+    e
+}
+
+pub(crate) fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
     } else {
-        Ok(PathBuf::from("/usr/local/bin"))
+        format!("{} {}s", count, noun)
     }
 }
 
-fn set_active(version: &str) -> Result<(), failure::Error> {
-    let bin = get_bin_path()?;
-
-    info!("Installing helm and tiller into {}", bin.to_str().unwrap());
-    let install_path = get_cache_path(version).join(format!("{}-{}", OS, ARCH));
+pub(crate) fn get_felloe_home() -> PathBuf {
+    dirs::home_dir().unwrap().join(INSTALLATION_DIR)
+}
 
-    let helm_path = install_path.join(HELM_BIN_NAME);
-    let tiller_path = install_path.join(TILLER_BIN_NAME);
+/// Total size in bytes of the files under `path`.
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    let entries = match path.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
 
-    let helm_sym_path = PathBuf::from(&bin).join(HELM_BIN_NAME);
-    let tiller_sym_path = PathBuf::from(&bin).join(TILLER_BIN_NAME);
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
 
-    if helm_path.exists() {
-        if helm_sym_path.exists() {
-            fs::remove_file(&helm_sym_path)?;
-        }
+pub(crate) fn get_bin_path() -> Result<PathBuf, failure::Error> {
+    if cfg!(target_os = "windows") {
+        let var = if cfg!(target_arch = "x86_64") {
+            "programfiles(x86)"
+        } else {
+            "programfiles"
+        };
+        let path = std::env::var(var)?;
 
-        // TODO fs::soft_link is deprecated but the equivalent function for windows is borked at this time
-        #[allow(deprecated)]
-        fs::soft_link(helm_path, helm_sym_path)?;
+        Ok(PathBuf::from(path).join("helm"))
     } else {
-        return Err(failure::err_msg(format!(
-            "Unable to set active helm {}. The executable does not exist at {}",
-            version,
-            helm_path.to_str().unwrap()
-        )));
+        Ok(PathBuf::from("/usr/local/bin"))
     }
+}
 
-    if tiller_path.exists() {
-        if tiller_sym_path.exists() {
-            fs::remove_file(&tiller_sym_path)?;
-        }
-        fs::hard_link(tiller_path, tiller_sym_path)?;
-    } else if version.contains("v2") {
-        warn!(
-            "Unable to set active tiller {}. The executable does not exist at {}",
-            version,
-            tiller_path.to_str().unwrap()
-        );
+/// Install the configured plugins and replay the repositories of the `previous` active
+/// version for a newly activated version, warning on failure.
+pub(crate) fn after_activate(felloe: &Felloe, previous: Option<String>, version: &str) {
+    if let Err(e) = plugins::ensure_configured(felloe, version) {
+        eprintln!("Warning: {}", e);
     }
 
-    Ok(())
+    let config = match felloe.config() {
+        Ok(config) => config,
+        Err(e) => return eprintln!("Warning: {}", e),
+    };
+
+    if let Some(previous) = previous.filter(|p| p != version) {
+        if config.repositories.sync_on_install {
+            let update = config.repositories.update;
+            if let Err(e) = repos::sync(felloe, &previous, version, update) {
+                eprintln!("Warning: {}", e);
+            }
+        }
+    }
 }
 
-pub fn get_installed_versions() -> Result<Vec<String>, failure::Error> {
-    let versions = dirs::home_dir()
-        .unwrap()
-        .join(&INSTALLATION_DIR)
-        .join("cache")
-        .read_dir()?
-        .map(|e| String::from(e.unwrap().file_name().to_str().unwrap()))
-        .collect();
+pub fn list(felloe: &Felloe, long: bool) -> Result<(), failure::Error> {
+    let installed = felloe.list_installed()?;
 
-    Ok(versions)
-}
+    if !long {
+        for version in installed {
+            println!("{}", version.version);
+        }
+        return Ok(());
+    }
 
-pub fn list() -> Result<(), failure::Error> {
-    let versions = get_installed_versions()?;
+    println!(
+        "  {:<14} {:<14} {:<14} PLATFORMS",
+        "VERSION", "DOWNLOADED", "LAST USED"
+    );
 
-    for version in versions {
-        println!("{}", version);
+    for version in installed {
+        let metadata = &version.metadata;
+        let platforms: Vec<String> = version.platforms.iter().map(Platform::to_string).collect();
+        let marker = if version.active { "*" } else { " " };
+
+        println!(
+            "{} {:<14} {:<14} {:<14} {}",
+            marker,
+            version.version,
+            metadata::days_ago(metadata.downloaded_at(&version.path)),
+            metadata::days_ago(metadata.last_used(&version.path)),
+            platforms.join(", ")
+        );
     }
 
     Ok(())
 }
 
-pub fn exec(version: String, args: Vec<String>) -> Result<(), failure::Error> {
-    let bin_path = get_cache_path(&version).join(format!("{}-{}", OS, ARCH));
+pub fn exec(felloe: &Felloe, version: String, args: Vec<String>) -> Result<(), failure::Error> {
+    let version = felloe.resolve(Some(&version))?;
+    let bin_path = felloe.install_path(&version, &Platform::current());
+    let helm_path = bin_path.join(HELM_BIN_NAME);
 
-    let path_envar = env::var_os("PATH").unwrap();
+    if !helm_path.exists() {
+        return Err(failure::err_msg(format!(
+            "A helm binary is not installed for {}",
+            version
+        )));
+    }
+
+    let (program, args) = match args.split_first() {
+        Some((program, args)) => (program, args),
+        None => return Err(failure::err_msg("A command to execute is required")),
+    };
+
+    let path_envar = env::var_os("PATH").unwrap_or_default();
     let mut paths = env::split_paths(&path_envar).collect::<Vec<_>>();
     paths.insert(0, bin_path);
-    let new_path = env::join_paths(paths).unwrap();
+    let new_path = env::join_paths(paths)?;
+
+    metadata::record_used(&felloe.cache_path(&version));
 
-    Command::new("helm")
+    info!("Executing {} with helm {} first on PATH", program, version);
+    let status = Command::new(program)
         .env("PATH", new_path)
+        .env("FELLOE_HELM_VERSION", &version)
+        .env("HELM_BIN", &helm_path)
+        .envs(helm_env(felloe, &version)?)
         .args(args)
-        .spawn()?
-        .wait_with_output()?;
+        .status()
+        .map_err(|e| failure::err_msg(format!("{} failed to start: {}", program, e)))?;
+
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
 
     Ok(())
 }
 
-pub fn prune() -> Result<(), failure::Error> {
-    let active_version = get_active_version()?;
-    let installed_versions = get_installed_versions()?;
+pub fn shell(
+    felloe: &Felloe,
+    version: Option<String>,
+    shell: Option<Shell>,
+    auto: bool,
+    unset: bool,
+) -> Result<(), failure::Error> {
+    let shell = shell.unwrap_or_else(shell::detect);
+    let current = env::var("FELLOE_HELM_VERSION").ok();
+    let auto_applied = env::var_os("FELLOE_SHELL_AUTO").is_some();
+
+    let mut project = None;
+    let version = if unset {
+        None
+    } else if let Some(version) = version {
+        Some(normalize_version(&version))
+    } else if auto && current.is_some() && !auto_applied {
+        debug!("Leaving manual shell override {:?} in place", current);
+        return Ok(());
+    } else {
+        match find_version_file(&env::current_dir()?) {
+            Some(file) => {
+                let version = read_version_file(&file)?;
+                project = Some(file);
+                Some(version)
+            }
+            None if !auto => {
+                return Err(failure::err_msg(format!(
+                    "A version is required when no {} file is found",
+                    VERSION_FILE_NAME
+                )))
+            }
+            None if auto_applied => None,
+            None => return Ok(()),
+        }
+    };
+
+    if auto && version.is_some() && version == current {
+        return Ok(());
+    }
 
-    let versions: Vec<String> = installed_versions
-        .into_iter()
-        .filter(|v| v != &active_version)
-        .collect();
+    // Remembered so prune keeps the version, also when the auto hook switches to it
+    if let Some(file) = &project {
+        remember_project(felloe, file);
+    }
 
-    remove(versions, false)?;
+    let path_envar = env::var_os("PATH").unwrap_or_default();
+    let previous = env::var_os("FELLOE_SHELL_PATH").map(PathBuf::from);
+    let mut paths = env::split_paths(&path_envar)
+        .filter(|p| Some(p) != previous.as_ref())
+        .collect::<Vec<_>>();
 
-    Ok(())
-}
+    let mut statements = Vec::new();
+    let config = felloe.config()?;
+
+    match version {
+        Some(version) => {
+            let bin_path = felloe.install_path(&version, &Platform::current());
 
-pub fn remove(versions: Vec<String>, force: bool) -> Result<(), failure::Error> {
-    let active_version = get_active_version()?;
-    if versions.contains(&active_version) {
-        if force {
-            let helm_sym_path = get_bin_path()?.join(HELM_BIN_NAME);
-            let tiller_sym_path = get_bin_path()?.join(TILLER_BIN_NAME);
+            if !bin_path.join(HELM_BIN_NAME).exists() {
+                let message = format!("helm {} is not installed", version);
+                if auto {
+                    eprintln!("felloe: {}", message);
+                    return Ok(());
+                }
+                return Err(failure::err_msg(message));
+            }
 
-            if helm_sym_path.exists() {
-                fs::remove_file(helm_sym_path)?;
+            metadata::record_used(&felloe.cache_path(&version));
+
+            paths.insert(0, bin_path.clone());
+            statements.push(shell::export(&shell, "FELLOE_HELM_VERSION", &version));
+            statements.push(shell::export(
+                &shell,
+                "FELLOE_SHELL_PATH",
+                &bin_path.to_string_lossy(),
+            ));
+
+            for (name, value) in isolation::helm_env(felloe, &version, &config) {
+                statements.push(shell::export(&shell, name, &value));
             }
 
-            if tiller_sym_path.exists() {
-                fs::remove_file(tiller_sym_path)?;
+            if auto {
+                statements.push(shell::export(&shell, "FELLOE_SHELL_AUTO", "1"));
+            } else {
+                statements.push(shell::unset(&shell, "FELLOE_SHELL_AUTO"));
+            }
+        }
+        None => {
+            statements.push(shell::unset(&shell, "FELLOE_HELM_VERSION"));
+            statements.push(shell::unset(&shell, "FELLOE_SHELL_PATH"));
+            statements.push(shell::unset(&shell, "FELLOE_SHELL_AUTO"));
+
+            if config.isolation != Isolation::None {
+                for name in isolation::HELM_ENV_VARS {
+                    statements.push(shell::unset(&shell, name));
+                }
             }
-        } else {
-            return Err(failure::err_msg(format!(
-                "Cannot remove active version: {}",
-                active_version
-            )));
         }
     }
 
-    for version in versions.iter() {
-        let version_path = get_cache_path(version);
+    let new_path = env::join_paths(paths)?;
+    let new_path = new_path
+        .to_str()
+        .ok_or_else(|| failure::err_msg("PATH contains a directory that is not valid UTF-8"))?;
+    statements.push(shell::export(&shell, "PATH", new_path));
 
-        if version_path.exists() {
-            info!("Uninstalling {}", version);
-            fs::remove_dir_all(version_path)?;
-        } else {
-            println!("Cache directory {} does not exist.", version);
-        }
+    for statement in statements {
+        println!("{}", statement);
     }
 
     Ok(())
 }
 
-pub fn run_helm(version: &str, args: Vec<String>) -> Result<(), failure::Error> {
-    let helm_path = get_cache_path(&version)
-        .join(format!("{}-{}", OS, ARCH))
+pub fn env(shell: Option<Shell>) -> Result<(), failure::Error> {
+    let shell = shell.unwrap_or_else(shell::detect);
+    print!("{}", shell::hook(&shell));
+
+    Ok(())
+}
+
+fn helm_command(felloe: &Felloe, version: &str) -> Result<Command, failure::Error> {
+    let helm_path = felloe
+        .install_path(version, &Platform::current())
         .join(HELM_BIN_NAME);
-    let command = Command::new(&helm_path)
-        .args(args)
-        .spawn()
-        .unwrap_or_else(|_| panic!("{} failed to start", helm_path.as_path().to_str().unwrap()));
 
-    command.wait_with_output()?;
+    if !helm_path.exists() {
+        return Err(failure::err_msg(format!(
+            "A helm binary is not installed for {}",
+            version
+        )));
+    }
+
+    let mut command = Command::new(&helm_path);
+    command.envs(helm_env(felloe, version)?);
+    Ok(command)
+}
+
+/// Run helm `version` with `args` on behalf of the user, recording it as used. Exits with
+/// helm's status when it fails.
+pub fn run(felloe: &Felloe, version: &str, args: Vec<String>) -> Result<(), failure::Error> {
+    let version = felloe.resolve(Some(version))?;
+    metadata::record_used(&felloe.cache_path(&version));
+
+    let status = helm_command(felloe, &version)?.args(args).status()?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
 
     Ok(())
 }
 
-pub fn uninstall() -> Result<(), failure::Error> {
-    let version = get_active_version()?;
+pub fn run_helm<I, S>(felloe: &Felloe, version: &str, args: I) -> Result<(), failure::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let status = helm_command(felloe, version)?.args(args).status()?;
 
-    println!("Uninstalling helm {}", version);
-    remove([version].to_vec(), true)?;
+    if !status.success() {
+        return Err(failure::err_msg(format!(
+            "helm {} exited with {}",
+            version, status
+        )));
+    }
 
-    println!("No active version set");
+    Ok(())
+}
+
+/// Run helm `version` with `args`, writing `input` to its standard input.
+pub fn run_helm_with_input<I, S>(
+    felloe: &Felloe,
+    version: &str,
+    args: I,
+    input: &str,
+) -> Result<(), failure::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut child = helm_command(felloe, version)?
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(failure::err_msg(format!(
+            "helm {} exited with {}",
+            version, status
+        )));
+    }
 
     Ok(())
 }
 
-pub fn get_active_version() -> Result<String, failure::Error> {
-    let sym_path = get_bin_path()?.join(HELM_BIN_NAME);
+/// Run helm `version` with `args` and capture its standard output.
+pub fn helm_output<I, S>(felloe: &Felloe, version: &str, args: I) -> Result<String, failure::Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = helm_command(felloe, version)?.args(args).output()?;
 
-    if !sym_path.exists() {
-        return Err(failure::err_msg("An active version is not set"));
+    if !output.status.success() {
+        return Err(failure::err_msg(format!(
+            "helm {} exited with {}: {}",
+            version,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
     }
 
-    let version = match fs::read_link(sym_path) {
-        Ok(path) => path
-            .parent()
-            .unwrap()
-            .parent()
-            .unwrap()
-            .file_name()
-            .unwrap()
-            .to_os_string(),
-        Err(_) => {
-            return Err(failure::err_msg(
-                "Unable to detect active version. {} is not a symbolic link.",
-            ));
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Environment variables pointing helm `version` at resources managed by felloe.
+fn helm_env(felloe: &Felloe, version: &str) -> Result<Vec<(&'static str, String)>, failure::Error> {
+    let mut vars = isolation::helm_env(felloe, version, &felloe.config()?);
+
+    if env::var_os("HELM_HOST").is_none() {
+        if let Some(host) = tiller::helm_host(felloe, version) {
+            debug!("Using local tiller at {}", host);
+            vars.push(("HELM_HOST", host));
         }
-    };
+    }
 
-    Ok(String::from(version.as_os_str().to_str().unwrap()))
+    Ok(vars)
+}
+
+pub fn uninstall(felloe: &Felloe, confirm: &Confirm) -> Result<(), failure::Error> {
+    let version = felloe
+        .active()?
+        .ok_or_else(|| failure::err_msg("An active version is not set"))?;
+
+    let versions = vec![version];
+
+    if !confirm.removal(felloe, &versions)? {
+        return Ok(());
+    }
+
+    println!("Uninstalling helm {}", versions[0]);
+    felloe.remove(&versions, true)?;
+
+    println!("No active version set");
+
+    Ok(())
 }
 
 pub fn versions(
+    felloe: &Felloe,
     filter: Option<String>,
     include_pre: bool,
     last: Option<usize>,
@@ -401,7 +741,7 @@ pub fn versions(
     let filter = filter.unwrap_or_default();
     let last = last.unwrap_or(25);
 
-    let releases = fetch_releases(last, include_pre)?;
+    let releases = felloe.fetch_releases(last, include_pre)?;
 
     releases
         .0
@@ -412,19 +752,23 @@ pub fn versions(
     Ok(())
 }
 
-pub fn which(version: Option<String>) -> Result<(), failure::Error> {
-    let version = version.unwrap_or(get_active_version()?);
+pub fn which(
+    felloe: &Felloe,
+    version: Option<String>,
+    platform: &Platform,
+) -> Result<(), failure::Error> {
+    let version = felloe.resolve(version.as_deref())?;
 
-    let install_path = get_cache_path(&version).join(format!("{}-{}", OS, ARCH));
-    let helm_path = install_path.join(HELM_BIN_NAME);
-    let tiller_path = install_path.join(TILLER_BIN_NAME);
+    let install_path = felloe.install_path(&version, platform);
+    let helm_path = install_path.join(platform.helm_bin_name());
+    let tiller_path = install_path.join(platform.tiller_bin_name());
 
     if helm_path.exists() {
         println!("{}", helm_path.as_path().to_str().unwrap());
     } else {
         return Err(failure::err_msg(format!(
-            "A helm binary is not installed for {}",
-            version
+            "A helm binary is not installed for {} on {}",
+            version, platform
         )));
     }
 
@@ -434,120 +778,3 @@ pub fn which(version: Option<String>) -> Result<(), failure::Error> {
 
     Ok(())
 }
-
-pub fn select_version() -> Result<(), failure::Error> {
-    let mut versions = get_installed_versions()?;
-    versions.sort();
-
-    let active_version = get_active_version()?;
-    let mut active_index = versions.iter().position(|v| v == &active_version).unwrap();
-
-    let mut stdout = stdout();
-    enter_alt_screen()?;
-    update_screen(&mut stdout, &versions, active_index)?;
-
-    let _raw = RawScreen::into_raw_mode()?;
-    let mut sync_stdin = input().read_sync();
-
-    loop {
-        let event = sync_stdin.next();
-
-        if let Some(key_event) = event {
-            if let InputEvent::Keyboard(k) = key_event {
-                match k {
-                    KeyEvent::Ctrl('c') | KeyEvent::Char('q') => {
-                        leave_alt_screen()?;
-
-                        break;
-                    }
-                    KeyEvent::Delete | KeyEvent::Backspace | KeyEvent::Char('d') => {
-                        let version = String::from(versions.to_vec().get(active_index).unwrap());
-                        leave_alt_screen()?;
-
-                        remove([version.clone()].to_vec(), false)?;
-                        queue!(stdout, Output(format!("Uninstalled {}\r\n", version)))?;
-                        break;
-                    }
-                    KeyEvent::Enter | KeyEvent::Char('i') => {
-                        let version = String::from(versions.to_vec().get(active_index).unwrap());
-                        leave_alt_screen()?;
-
-                        set_active(&version)?;
-                        queue!(stdout, Output(format!("\rActivated {}\r\n", version)))?;
-                        break;
-                    }
-                    KeyEvent::Up | KeyEvent::Char('w') | KeyEvent::Char('k') => {
-                        if active_index > 0 {
-                            active_index -= 1;
-                        }
-
-                        update_screen(&mut stdout, &versions, active_index)?;
-                    }
-                    KeyEvent::Down | KeyEvent::Char('s') | KeyEvent::Char('j') => {
-                        if active_index < versions.len() - 1 {
-                            active_index += 1;
-                        }
-
-                        update_screen(&mut stdout, &versions, active_index)?;
-                    }
-                    _ => { /* Default case: do nothing*/ }
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
-
-fn update_screen<W: Write>(
-    w: &mut W,
-    versions: &[String],
-    active: usize,
-) -> Result<(), failure::Error> {
-    queue!(w, MoveTo(0, 5), Clear(ClearType::FromCursorDown))?;
-    for (i, v) in versions.iter().enumerate() {
-        if i == active {
-            queue!(
-                w,
-                SetForegroundColor(Color::Blue),
-                Output("  ‣ "),
-                Output(v),
-                SetAttribute(Attribute::Reset)
-            )?;
-        } else {
-            queue!(w, Output("    "), Output(v))?;
-        }
-
-        queue!(w, Output("\r\n"))?;
-    }
-    w.flush()?;
-    Ok(())
-}
-
-fn enter_alt_screen() -> Result<(), failure::Error> {
-    let mut stdout = stdout();
-    queue!(stdout, EnterAlternateScreen)?;
-    queue!(stdout, Hide)?;
-    queue!(stdout, Clear(ClearType::All))?;
-    queue!(stdout, MoveTo(0, 0))?;
-    queue!(
-        stdout,
-        Output(
-            "Move ↑↓ to select an installed version\nReturn key (i) to activate\nDelete key (d) to uninstall\nq to quit".to_string(),
-        )
-    )?;
-
-    stdout.flush()?;
-
-    Ok(())
-}
-
-fn leave_alt_screen() -> Result<(), failure::Error> {
-    let mut stdout = stdout();
-
-    queue!(stdout, Show)?;
-    queue!(stdout, LeaveAlternateScreen)?;
-    stdout.flush()?;
-
-    Ok(())
-}
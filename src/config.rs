@@ -0,0 +1,228 @@
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Settings read from `~/.felloe/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Give helm versions their own configuration, data and cache directories
+    pub isolation: Isolation,
+
+    /// Plugins installed whenever a version is installed or activated
+    pub plugins: Vec<PluginSpec>,
+
+    pub repositories: RepositoriesConfig,
+
+    pub notify: NotifyConfig,
+
+    pub prune: PruneConfig,
+
+    pub downloads: DownloadsConfig,
+
+    pub trash: TrashConfig,
+
+    pub picker: PickerConfig,
+
+    pub releases: ReleasesConfig,
+}
+
+/// Where helm releases are discovered, for internal forks and patched builds.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ReleasesConfig {
+    pub source: ReleaseSourceKind,
+    /// GitHub repository (default `helm/helm`) or repository in the OCI registry
+    pub repository: Option<String>,
+    /// URL or path of the release index or directory listing
+    pub url: Option<String>,
+    /// OCI registry host, e.g. `ghcr.io`
+    pub registry: Option<String>,
+    /// Where the `github` and `index` sources download archives GitHub does not host, instead of
+    /// the Felloe mirror
+    pub mirror: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseSourceKind {
+    /// Releases of a GitHub repository
+    #[default]
+    Github,
+    /// A JSON or YAML file listing releases
+    Index,
+    /// Archive names in the directory listing of a mirror, the download mirror by default
+    Directory,
+    /// Version tags of an OCI repository
+    Oci,
+}
+
+impl fmt::Display for ReleaseSourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReleaseSourceKind::Github => write!(f, "github"),
+            ReleaseSourceKind::Index => write!(f, "index"),
+            ReleaseSourceKind::Directory => write!(f, "directory"),
+            ReleaseSourceKind::Oci => write!(f, "oci"),
+        }
+    }
+}
+
+/// Look and key bindings of the interactive picker.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PickerConfig {
+    pub theme: PickerTheme,
+    /// Mark the selection with reverse video and plain ASCII instead of color and symbols
+    pub accessible: bool,
+    /// Colors replacing the theme's, e.g. `selected = "cyan"`
+    pub colors: PickerColors,
+    /// Keys replacing the default bindings of each action
+    pub keys: PickerKeys,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PickerTheme {
+    /// The selection in blue, remote versions and key hints dimmed
+    #[default]
+    Default,
+    /// The selection in bold reverse video, nothing dimmed
+    HighContrast,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PickerColors {
+    pub selected: Option<String>,
+    pub remote: Option<String>,
+    pub hints: Option<String>,
+}
+
+/// Key names such as `k`, `G`, `up`, `page-down`, `enter`, `space` or `ctrl-d` for each action.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PickerKeys {
+    pub up: Option<Vec<String>>,
+    pub down: Option<Vec<String>>,
+    pub page_up: Option<Vec<String>>,
+    pub page_down: Option<Vec<String>>,
+    pub top: Option<Vec<String>>,
+    pub bottom: Option<Vec<String>>,
+    pub search: Option<Vec<String>>,
+    pub select: Option<Vec<String>>,
+    pub mark: Option<Vec<String>>,
+    pub remove: Option<Vec<String>>,
+    pub download: Option<Vec<String>>,
+    pub remote: Option<Vec<String>>,
+    pub quit: Option<Vec<String>>,
+}
+
+/// Whether removed versions are kept in a trash directory for `felloe restore`.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TrashConfig {
+    /// Move removed versions to the trash instead of deleting them
+    pub enabled: bool,
+    /// How long versions stay in the trash before they are deleted, e.g. 7d
+    pub keep_for: String,
+}
+
+impl Default for TrashConfig {
+    fn default() -> TrashConfig {
+        TrashConfig {
+            enabled: false,
+            keep_for: "7d".to_string(),
+        }
+    }
+}
+
+/// Where verified release archives are kept for reinstalling without downloading again.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DownloadsConfig {
+    /// Directory of the download cache, which may be shared by several felloe homes
+    pub path: Option<PathBuf>,
+}
+
+/// Versions `prune` never removes.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PruneConfig {
+    pub keep: Vec<String>,
+}
+
+/// Whether and how often to check for new helm releases in the background.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotifyConfig {
+    /// Print a notice after commands when a newer release than the active version is available
+    pub enabled: bool,
+    /// Hours between checks for new releases
+    pub interval_hours: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> NotifyConfig {
+        NotifyConfig {
+            enabled: false,
+            interval_hours: 24,
+        }
+    }
+}
+
+/// How chart repositories carry over to newly installed versions.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RepositoriesConfig {
+    /// Add the previously active version's repositories when installing or activating a version
+    pub sync_on_install: bool,
+    /// Run `helm repo update` after adding repositories
+    pub update: bool,
+}
+
+/// A helm plugin and the version it is pinned to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PluginSpec {
+    pub name: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Isolation {
+    /// Every version shares helm's default directories
+    #[default]
+    None,
+    /// Each version gets its own directories
+    Version,
+    /// Versions with the same major version share directories
+    Major,
+}
+
+impl Config {
+    /// Load the config file at `path`, falling back to defaults when it does not exist.
+    pub fn load(path: &Path) -> Result<Config, failure::Error> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        debug!("Loading config from {:?}", path);
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| failure::err_msg(format!("Invalid config file {}: {}", path.display(), e)))
+    }
+}
+
+impl FromStr for Isolation {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Isolation, failure::Error> {
+        Ok(toml::Value::String(s.to_string()).try_into()?)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::cli::ConfirmOpts;
+use crate::commands::{dir_size, plural};
+use crate::felloe::Felloe;
+use atty::Stream;
+use indicatif::HumanBytes;
+use std::io::{stdin, stdout, Write};
+
+/// How destructive commands go ahead: after asking, without asking (`--yes`) or not at all
+/// (`--dry-run`).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Confirm {
+    pub yes: bool,
+    pub dry_run: bool,
+}
+
+impl From<ConfirmOpts> for Confirm {
+    fn from(opts: ConfirmOpts) -> Confirm {
+        Confirm {
+            yes: opts.yes,
+            dry_run: opts.dry_run,
+        }
+    }
+}
+
+impl Confirm {
+    /// List `versions` with the space they use and ask whether to remove them. Dry runs only
+    /// print the list.
+    pub fn removal(&self, felloe: &Felloe, versions: &[String]) -> Result<bool, failure::Error> {
+        let sizes: Vec<u64> = versions
+            .iter()
+            .map(|v| dir_size(&felloe.cache_path(v)))
+            .collect();
+        let total = HumanBytes(sizes.iter().sum());
+
+        if self.dry_run {
+            for (version, size) in versions.iter().zip(&sizes) {
+                println!("Would remove helm {} ({})", version, HumanBytes(*size));
+            }
+            println!("Would reclaim {}", total);
+            return Ok(false);
+        }
+
+        if self.yes {
+            return Ok(true);
+        }
+
+        for (version, size) in versions.iter().zip(&sizes) {
+            println!("helm {} ({})", version, HumanBytes(*size));
+        }
+
+        let question = if felloe.config()?.trash.enabled {
+            format!(
+                "Move {} ({}) to the trash?",
+                plural(versions.len(), "version"),
+                total
+            )
+        } else {
+            format!(
+                "Remove {} and reclaim {}?",
+                plural(versions.len(), "version"),
+                total
+            )
+        };
+
+        ask(&question)
+    }
+}
+
+/// Ask a yes/no question, defaulting to no. Answers are read from stdin even when it is not a
+/// terminal, so `yes | felloe …` works. When stdin is closed there is nobody to answer, so the
+/// command fails and points at `--yes` instead.
+pub fn ask(question: &str) -> Result<bool, failure::Error> {
+    print!("{} [y/N] ", question);
+    stdout().flush()?;
+
+    let mut answer = String::new();
+    if stdin().read_line(&mut answer)? == 0 {
+        println!();
+        return Err(failure::err_msg(format!(
+            "{} No answer on stdin. Pass --yes to confirm without asking",
+            question
+        )));
+    }
+
+    if !atty::is(Stream::Stdin) {
+        println!("{}", answer.trim());
+    }
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes"))
+}
@@ -1,6 +1,12 @@
-pub static GH_RELEASES_API: &str = "https://api.github.com/repos/helm/helm/releases";
 pub static HELM_DOWNLOAD_URL: &str = "https://get.helm.sh";
 pub static INSTALLATION_DIR: &str = ".felloe";
+pub static VERSION_FILE_NAME: &str = ".helm-version";
+pub static CONFIG_FILE_NAME: &str = "config.toml";
+pub static LOCK_FILE_NAME: &str = "felloe.lock";
+pub static PROJECTS_FILE_NAME: &str = "projects";
+pub static METADATA_FILE_NAME: &str = "felloe.json";
+pub static ARCHIVES_DIR_NAME: &str = "downloads";
+pub static TRASH_DIR_NAME: &str = "trash";
 
 #[cfg(target_os = "macos")]
 pub static OS: &str = "darwin";
@@ -11,24 +17,28 @@ pub static OS: &str = "linux";
 #[cfg(target_os = "windows")]
 pub static OS: &str = "windows";
 
-#[cfg(any(target_arch = "x86", target_arch = "i686", target_arch = "i386"))]
+#[cfg(target_arch = "x86")]
 pub static ARCH: &str = "386";
 
 #[cfg(target_arch = "x86_64")]
 pub static ARCH: &str = "amd64";
 
-#[cfg(target_arch = "powerpc64le")]
+#[cfg(all(target_arch = "powerpc64", target_endian = "little"))]
 pub static ARCH: &str = "ppc64le";
 
 #[cfg(target_arch = "s390x")]
 pub static ARCH: &str = "s390x";
 
-#[cfg(any(target_arch = "arm", target_arch = "armv7"))]
+#[cfg(target_arch = "arm")]
 pub static ARCH: &str = "arm";
 
 #[cfg(target_arch = "aarch64")]
 pub static ARCH: &str = "arm64";
 
+pub static SUPPORTED_OSES: &[&str] = &["darwin", "linux", "windows"];
+
+pub static SUPPORTED_ARCHS: &[&str] = &["386", "amd64", "arm", "arm64", "ppc64le", "s390x"];
+
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 pub static HELM_BIN_NAME: &str = "helm";
 
@@ -41,6 +51,22 @@ pub static HELM_BIN_NAME: &str = "helm.exe";
 #[cfg(target_os = "windows")]
 pub static TILLER_BIN_NAME: &str = "tiller.exe";
 
+pub static HELM_2TO3_PLUGIN_URL: &str = "https://github.com/helm/helm-2to3";
+
+pub static TILLER_PID_FILE: &str = "tiller.pid";
+pub static TILLER_STATE_FILE: &str = "tiller.json";
+pub static TILLER_LOG_FILE: &str = "tiller.log";
+
+/// Concurrent downloads without `--jobs`. The CLI spells it out as its default value
+pub static DEFAULT_DOWNLOAD_JOBS: usize = 4;
+
+pub static RELEASE_INDEX_FILE: &str = "releases.json";
+/// Number of recent releases compared against when looking for newer versions
+pub static RELEASE_INDEX_SIZE: usize = 100;
+
+/// Percentage interval between plain progress lines
+pub static PLAIN_PROGRESS_STEP: u64 = 10;
+
 pub static BAR_STYLE_TEMPLATE: &str =
     "{spinner:.green} {msg} {percent}% {bar:40.cyan/blue} {bytes}/{total_bytes} eta: {eta}";
 pub static BAR_PROGRESS_CHARS: &str = "#>-";
@@ -0,0 +1,117 @@
+use crate::archives::{get_archives_path, stored};
+use crate::commands::{dir_size, plural};
+use crate::felloe::Felloe;
+use crate::isolation::get_homes_path;
+use crate::metadata::{age, days_ago, VersionMetadata};
+use crate::prune::{self, PruneOptions};
+use crate::release::Version;
+use crate::trash::get_trash_path;
+use indicatif::HumanBytes;
+
+/// Show the space each downloaded version uses, flag versions not used for `unused_for` and
+/// suggest the `prune` that would remove them.
+pub fn du(felloe: &Felloe, unused_for: &str) -> Result<(), failure::Error> {
+    let stale_after = prune::parse_age(unused_for)?;
+    let active = felloe.active().unwrap_or_default();
+
+    let mut installed = felloe.installed_versions().unwrap_or_default();
+    installed.sort_by(|a, b| match (Version::parse(a), Version::parse(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    });
+
+    println!("  {:<14} {:>10}  LAST USED", "VERSION", "SIZE");
+
+    let mut total = 0;
+    for version in &installed {
+        let dir = felloe.cache_path(version);
+        let size = dir_size(&dir);
+        total += size;
+
+        let used = VersionMetadata::load_from(&dir).last_used(&dir);
+        let last_used = if age(used).is_some_and(|age| age >= stale_after) {
+            format!("{}, stale", days_ago(used))
+        } else {
+            days_ago(used)
+        };
+        let marker = if active.as_ref() == Some(version) {
+            "*"
+        } else {
+            " "
+        };
+
+        println!(
+            "{} {:<14} {:>10}  {}",
+            marker,
+            version,
+            HumanBytes(size).to_string(),
+            last_used
+        );
+    }
+
+    println!();
+    println!(
+        "{:<16} {:>10}",
+        plural(installed.len(), "version"),
+        HumanBytes(total).to_string()
+    );
+
+    let homes = get_homes_path(felloe);
+    if homes.exists() {
+        println!(
+            "{:<16} {:>10}",
+            "isolated homes",
+            HumanBytes(dir_size(&homes)).to_string()
+        );
+    }
+
+    let archives = stored(felloe)?;
+    let archives_size: u64 = archives.iter().map(|a| a.size).sum();
+    if !archives.is_empty() {
+        println!(
+            "{:<16} {:>10}",
+            "download cache",
+            HumanBytes(archives_size).to_string()
+        );
+    }
+
+    let trash = get_trash_path(felloe);
+    if trash.exists() {
+        println!(
+            "{:<16} {:>10}",
+            "trash",
+            HumanBytes(dir_size(&trash)).to_string()
+        );
+    }
+
+    // A download cache shared through `downloads.path` may live outside the felloe home
+    let home = felloe.home();
+    let mut total = dir_size(home);
+    if !get_archives_path(felloe)?.starts_with(home) {
+        total += archives_size;
+    }
+
+    println!("{:<16} {:>10}", "total", HumanBytes(total).to_string());
+
+    let stale = prune::removable(
+        felloe,
+        &PruneOptions {
+            unused_for: Some(stale_after),
+            ..PruneOptions::default()
+        },
+    )?;
+
+    if !stale.is_empty() {
+        let reclaimable: u64 = stale.iter().map(|v| dir_size(&felloe.cache_path(v))).sum();
+
+        println!();
+        println!(
+            "Run `felloe prune --unused-for {}` to remove {} and reclaim {}",
+            unused_for,
+            plural(stale.len(), "stale version"),
+            HumanBytes(reclaimable)
+        );
+    }
+
+    Ok(())
+}
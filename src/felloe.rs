@@ -0,0 +1,562 @@
+use crate::archives;
+use crate::commands::{download_with, get_bin_path, get_felloe_home, unpack_release, Fetched};
+use crate::config::{Config, Isolation};
+use crate::constants::*;
+use crate::metadata::{self, VersionMetadata};
+use crate::platform::Platform;
+use crate::progress::{ProgressMode, Reporter, Task};
+use crate::project::{find_version_file, normalize_version, read_version_file};
+use crate::release::{by_version, is_helm2, Release, Releases, Version};
+use crate::source::{self, ReleaseArchive, ReleaseSource};
+use crate::trash;
+use log::*;
+use reqwest::Client;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A downloaded helm version.
+#[derive(Debug, Clone)]
+pub struct InstalledVersion {
+    pub version: String,
+    /// Directory holding the binaries of every platform downloaded for the version
+    pub path: PathBuf,
+    pub platforms: Vec<Platform>,
+    pub active: bool,
+    pub metadata: VersionMetadata,
+}
+
+/// The outcome of activating a version.
+#[derive(Debug, Clone)]
+pub struct Activated {
+    pub version: String,
+    /// The version that was active before, if any
+    pub previous: Option<String>,
+}
+
+/// Manages helm versions under a felloe home, for embedding felloe in other tools. Methods
+/// return typed results and leave printing to the caller.
+///
+/// ```no_run
+/// use felloe::{platform::Platform, Felloe};
+///
+/// let felloe = Felloe::from_env()?.with_home("/opt/felloe".into());
+/// let (version, _) = felloe.install("3.2.4", &Platform::current())?;
+/// felloe.activate(&version)?;
+/// # Ok::<(), failure::Error>(())
+/// ```
+///
+/// The home holds downloaded versions and everything else felloe keeps: the config file, the
+/// trash, the download cache unless it is configured elsewhere, and per-version helm homes.
+#[derive(Debug, Clone)]
+pub struct Felloe {
+    home: PathBuf,
+    bin_dir: PathBuf,
+    client: Client,
+    mirror: String,
+    /// Set by `with_config_path`, otherwise the config file in the home
+    config_path: Option<PathBuf>,
+    /// Overrides the configured isolation mode
+    isolation: Option<Isolation>,
+    /// Overrides the configured download cache
+    downloads_dir: Option<PathBuf>,
+    /// Set by `with_source`, otherwise built from the config file whenever releases are needed
+    source: Option<Arc<dyn ReleaseSource>>,
+    reporter: Reporter,
+}
+
+impl Felloe {
+    /// The felloe the CLI uses: `~/.felloe`, the system bin directory, release archives from
+    /// get.helm.sh and releases discovered through the configured release source. The config
+    /// file, isolation mode and download cache can be overridden with `FELLOE_CONFIG`,
+    /// `FELLOE_ISOLATION` and `FELLOE_DOWNLOADS`.
+    pub fn from_env() -> Result<Felloe, failure::Error> {
+        let isolation = match env::var("FELLOE_ISOLATION") {
+            Ok(isolation) => Some(isolation.parse()?),
+            Err(_) => None,
+        };
+
+        Ok(Felloe {
+            home: get_felloe_home(),
+            bin_dir: get_bin_path()?,
+            client: Client::new(),
+            mirror: HELM_DOWNLOAD_URL.to_string(),
+            config_path: env::var_os("FELLOE_CONFIG").map(PathBuf::from),
+            isolation,
+            downloads_dir: env::var_os("FELLOE_DOWNLOADS").map(PathBuf::from),
+            source: None,
+            reporter: Reporter::new(ProgressMode::detect()),
+        })
+    }
+
+    pub fn with_home(mut self, home: PathBuf) -> Felloe {
+        self.home = home;
+        self
+    }
+
+    /// Where the active version's helm and tiller are linked.
+    pub fn with_bin_dir(mut self, bin_dir: PathBuf) -> Felloe {
+        self.bin_dir = bin_dir;
+        self
+    }
+
+    pub fn with_client(mut self, client: Client) -> Felloe {
+        self.client = client;
+        self
+    }
+
+    /// Base URL release archives and their digests are downloaded from.
+    pub fn with_mirror(mut self, mirror: String) -> Felloe {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Read the config file at `path` instead of the one in the home.
+    pub fn with_config_path(mut self, path: PathBuf) -> Felloe {
+        self.config_path = Some(path);
+        self
+    }
+
+    /// Isolate helm versions this way whatever the config file says.
+    pub fn with_isolation(mut self, isolation: Isolation) -> Felloe {
+        self.isolation = Some(isolation);
+        self
+    }
+
+    /// Keep verified archives in `dir` whatever the config file says.
+    pub fn with_downloads_dir(mut self, dir: PathBuf) -> Felloe {
+        self.downloads_dir = Some(dir);
+        self
+    }
+
+    /// Where releases are discovered, e.g. `GitHub::new("my-org/helm", client)`.
+    pub fn with_source(mut self, source: impl ReleaseSource + 'static) -> Felloe {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn with_reporter(mut self, reporter: Reporter) -> Felloe {
+        self.reporter = reporter;
+        self
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    pub fn bin_dir(&self) -> &Path {
+        &self.bin_dir
+    }
+
+    pub fn reporter(&self) -> &Reporter {
+        &self.reporter
+    }
+
+    /// The config file given to `with_config_path`, otherwise the one in the home.
+    pub fn config_path(&self) -> PathBuf {
+        self.config_path
+            .clone()
+            .unwrap_or_else(|| self.home.join(CONFIG_FILE_NAME))
+    }
+
+    /// The download cache given to `with_downloads_dir`, if any.
+    pub fn downloads_dir(&self) -> Option<&Path> {
+        self.downloads_dir.as_deref()
+    }
+
+    /// The config file of this felloe, or defaults when there is none, with the isolation mode
+    /// given to `with_isolation`.
+    pub fn config(&self) -> Result<Config, failure::Error> {
+        let mut config = Config::load(&self.config_path())?;
+        if let Some(isolation) = self.isolation {
+            config.isolation = isolation;
+        }
+
+        Ok(config)
+    }
+
+    pub fn cache_path(&self, version: &str) -> PathBuf {
+        self.home.join("cache").join(version)
+    }
+
+    pub fn install_path(&self, version: &str, platform: &Platform) -> PathBuf {
+        self.cache_path(version).join(platform.to_string())
+    }
+
+    pub fn is_installed(&self, version: &str, platform: &Platform) -> bool {
+        self.install_path(version, platform)
+            .join(platform.helm_bin_name())
+            .exists()
+    }
+
+    /// URL of the release archive for `version` on `platform`.
+    pub fn release_url(&self, version: &str, platform: &Platform) -> String {
+        format!(
+            "{}/{}",
+            self.mirror.trim_end_matches('/'),
+            platform.archive_name(version)
+        )
+    }
+
+    /// Where releases are discovered: the source given to `with_source`, or the one selected by
+    /// the config file. Only commands that look up releases read the `[releases]` settings, so a
+    /// broken source never gets in the way of the others.
+    pub fn source(&self) -> Result<Arc<dyn ReleaseSource>, failure::Error> {
+        if let Some(source) = &self.source {
+            return Ok(source.clone());
+        }
+
+        let source = source::from_config(&self.config()?.releases, &self.client, &self.mirror)?;
+        Ok(Arc::from(source))
+    }
+
+    /// Where the archive of `release` for `platform` is downloaded from and the digest it must
+    /// match: published by the release source, or next to the archive on the mirror.
+    pub fn archive(
+        &self,
+        release: &Release,
+        platform: &Platform,
+    ) -> Result<ReleaseArchive, failure::Error> {
+        let version = &release.tag_name;
+        let archive = match self.source()?.archive(release, platform)? {
+            Some(archive) => archive,
+            None => ReleaseArchive {
+                url: self.release_url(version, platform),
+                sha256: self.fetch_digest(version, platform)?,
+            },
+        };
+
+        archives::validate_digest(&archive.sha256)?;
+        Ok(archive)
+    }
+
+    /// The release of `version`, or of the latest version for `latest`.
+    pub fn fetch_release(&self, version: &str) -> Result<Release, failure::Error> {
+        self.source()?.release(version)
+    }
+
+    /// Up to `count` of the newest releases, oldest first.
+    pub fn fetch_releases(
+        &self,
+        count: usize,
+        include_pre: bool,
+    ) -> Result<Releases, failure::Error> {
+        let mut releases: Vec<Release> = self
+            .source()?
+            .releases(count)?
+            .into_iter()
+            .filter(|rel| !rel.prerelease || include_pre)
+            .collect();
+
+        releases.sort_by(by_version);
+        Ok(Releases(releases))
+    }
+
+    /// Names of the downloaded versions, in no particular order.
+    pub fn installed_versions(&self) -> Result<Vec<String>, failure::Error> {
+        let root = self.home.join("cache");
+        if !root.exists() {
+            return Ok(Vec::new());
+        }
+
+        read_installed(&root)
+    }
+
+    /// Downloaded versions, oldest first.
+    pub fn list_installed(&self) -> Result<Vec<InstalledVersion>, failure::Error> {
+        let active = self.active()?;
+
+        let mut installed = Vec::new();
+        for version in self.installed_versions()? {
+            let path = self.cache_path(&version);
+
+            let mut platforms: Vec<String> = path
+                .read_dir()?
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect();
+            platforms.sort();
+
+            installed.push(InstalledVersion {
+                active: active.as_ref() == Some(&version),
+                platforms: platforms.iter().filter_map(|p| p.parse().ok()).collect(),
+                metadata: VersionMetadata::load_from(&path),
+                path,
+                version,
+            });
+        }
+
+        installed.sort_by(
+            |a, b| match (Version::parse(&a.version), Version::parse(&b.version)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => a.version.cmp(&b.version),
+            },
+        );
+        Ok(installed)
+    }
+
+    /// The version linked into the bin directory, if any.
+    pub fn active(&self) -> Result<Option<String>, failure::Error> {
+        read_active(&self.bin_dir)
+    }
+
+    /// The helm version in effect: `version` if given, the shell override, the nearest
+    /// `.helm-version` file and finally the active version.
+    pub fn resolve(&self, version: Option<&str>) -> Result<String, failure::Error> {
+        if let Some(version) = version {
+            return Ok(normalize_version(version));
+        }
+
+        if let Ok(version) = env::var("FELLOE_HELM_VERSION") {
+            return Ok(version);
+        }
+
+        if let Some(file) = find_version_file(&env::current_dir()?) {
+            return read_version_file(&file);
+        }
+
+        self.active()?
+            .ok_or_else(|| failure::err_msg("An active version is not set"))
+    }
+
+    /// Download and verify `version` for `platform` without activating it, returning the
+    /// version's tag and whether it had to be downloaded.
+    pub fn install(
+        &self,
+        version: &str,
+        platform: &Platform,
+    ) -> Result<(String, Fetched), failure::Error> {
+        let task = self.reporter.task();
+
+        self.fetch(version, platform, &task).map_err(|e| {
+            task.fail(&format!("helm {} failed: {}", version, e));
+            e
+        })
+    }
+
+    /// Like `install`, reporting progress on `task`.
+    pub fn fetch(
+        &self,
+        version: &str,
+        platform: &Platform,
+        task: &Task,
+    ) -> Result<(String, Fetched), failure::Error> {
+        let version = normalize_version(version);
+        let cached = |tag: &str| {
+            task.finish(&format!("helm {} already cached", tag));
+            Ok((tag.to_string(), Fetched::Cached))
+        };
+
+        if version != "latest" && self.is_installed(&version, platform) {
+            return cached(&version);
+        }
+
+        let release = self.fetch_release(&version)?;
+        if self.is_installed(&release.tag_name, platform) {
+            return cached(&release.tag_name);
+        }
+
+        info!("Downloading helm {} for {}", release.tag_name, platform);
+        let archive = self.archive(&release, platform)?;
+        let fetched = self.download_verified(
+            &release.tag_name,
+            platform,
+            &archive.url,
+            &archive.sha256,
+            task,
+        )?;
+        Ok((release.tag_name, fetched))
+    }
+
+    /// Download, verify and unpack `version` for `platform`, reporting every stage on `task`.
+    /// Archives already in the download cache are unpacked without downloading them again.
+    pub fn download(
+        &self,
+        version: &str,
+        platform: &Platform,
+        task: &Task,
+    ) -> Result<Fetched, failure::Error> {
+        let archive = self.archive(&Release::from_tag(version), platform)?;
+
+        self.download_verified(version, platform, &archive.url, &archive.sha256, task)
+    }
+
+    /// The sha256 digest published next to the release archive for `version` on `platform` on
+    /// the mirror.
+    pub fn fetch_digest(
+        &self,
+        version: &str,
+        platform: &Platform,
+    ) -> Result<String, failure::Error> {
+        let sha_url = format!("{}.sha256", self.release_url(version, platform));
+        let sha = String::from_utf8(download_with(&self.client, &sha_url, &Task::hidden())?)?;
+
+        sha.split_whitespace()
+            .next()
+            .map(String::from)
+            .ok_or_else(|| {
+                failure::err_msg(format!("No sha256 digest published for helm {}", version))
+            })
+    }
+
+    /// Download `version` from `url`, or take it from the download cache, and unpack it only if
+    /// it matches the `sha` digest.
+    pub fn download_verified(
+        &self,
+        version: &str,
+        platform: &Platform,
+        url: &str,
+        sha: &str,
+        task: &Task,
+    ) -> Result<Fetched, failure::Error> {
+        let dir = self.cache_path(version);
+
+        if let Some(file) = archives::find(self, sha) {
+            unpack_release(self, version, platform, &file, sha, &dir, task)?;
+            return Ok(Fetched::Stored);
+        }
+
+        let file =
+            self.source()?
+                .download(&self.client, url, &platform.archive_name(version), task)?;
+        unpack_release(self, version, platform, &file, sha, &dir, task)?;
+
+        Ok(Fetched::Downloaded(file.len() as u64))
+    }
+
+    /// Link the downloaded `version` into the bin directory.
+    pub fn activate(&self, version: &str) -> Result<Activated, failure::Error> {
+        let previous = self.active().unwrap_or_default();
+
+        info!("Linking helm and tiller into {:?}", self.bin_dir);
+        let install_path = self.install_path(version, &Platform::current());
+
+        let helm_path = install_path.join(HELM_BIN_NAME);
+        let tiller_path = install_path.join(TILLER_BIN_NAME);
+
+        let helm_sym_path = self.bin_dir.join(HELM_BIN_NAME);
+        let tiller_sym_path = self.bin_dir.join(TILLER_BIN_NAME);
+
+        if helm_path.exists() {
+            if helm_sym_path.exists() {
+                fs::remove_file(&helm_sym_path)?;
+            }
+
+            // TODO fs::soft_link is deprecated but the equivalent function for windows is borked at this time
+            #[allow(deprecated)]
+            fs::soft_link(helm_path, helm_sym_path)?;
+        } else {
+            return Err(failure::err_msg(format!(
+                "Unable to set active helm {}. The executable does not exist at {}",
+                version,
+                helm_path.to_str().unwrap()
+            )));
+        }
+
+        if tiller_path.exists() {
+            if tiller_sym_path.exists() {
+                fs::remove_file(&tiller_sym_path)?;
+            }
+            fs::hard_link(tiller_path, tiller_sym_path)?;
+        } else if is_helm2(version) {
+            warn!(
+                "Unable to set active tiller {}. The executable does not exist at {}",
+                version,
+                tiller_path.to_str().unwrap()
+            );
+        }
+
+        metadata::record_used(&self.cache_path(version));
+
+        Ok(Activated {
+            version: version.to_string(),
+            previous,
+        })
+    }
+
+    /// Check that `versions` can be removed: all of them are downloaded, and the active version
+    /// is only among them with `force`.
+    pub fn check_removal(&self, versions: &[String], force: bool) -> Result<(), failure::Error> {
+        if let Some(version) = versions.iter().find(|v| !self.cache_path(v).exists()) {
+            return Err(failure::err_msg(format!(
+                "helm {} is not installed",
+                version
+            )));
+        }
+
+        match self.active().unwrap_or_default() {
+            Some(active) if !force && versions.contains(&active) => Err(failure::err_msg(format!(
+                "Cannot remove active version: {}",
+                active
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove downloaded versions, moving them to the trash when it is enabled. The active
+    /// version is only removed with `force`, which also unlinks it.
+    pub fn remove(&self, versions: &[String], force: bool) -> Result<(), failure::Error> {
+        self.check_removal(versions, force)?;
+
+        if let Some(active) = self.active().unwrap_or_default() {
+            if versions.contains(&active) {
+                for name in [HELM_BIN_NAME, TILLER_BIN_NAME] {
+                    let link = self.bin_dir.join(name);
+                    if link.exists() {
+                        fs::remove_file(link)?;
+                    }
+                }
+            }
+        }
+
+        for version in versions {
+            info!("Uninstalling {}", version);
+            trash::discard(self, version, &self.cache_path(version))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Names of the version directories under `root`. Names that are not valid UTF-8 cannot be
+/// versions and are skipped.
+fn read_installed(root: &Path) -> Result<Vec<String>, failure::Error> {
+    let mut versions = Vec::new();
+
+    for entry in root.read_dir()? {
+        match entry?.file_name().into_string() {
+            Ok(name) => versions.push(name),
+            Err(name) => debug!("Skipping {:?} in {}", name, root.display()),
+        }
+    }
+
+    Ok(versions)
+}
+
+/// The version whose helm is linked into `bin_dir`.
+fn read_active(bin_dir: &Path) -> Result<Option<String>, failure::Error> {
+    let sym_path = bin_dir.join(HELM_BIN_NAME);
+
+    if !sym_path.exists() {
+        return Ok(None);
+    }
+
+    let version = match fs::read_link(&sym_path) {
+        Ok(path) => path
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned()),
+        Err(_) => {
+            return Err(failure::err_msg(format!(
+                "Unable to detect active version. {} is not a symbolic link.",
+                sym_path.display()
+            )));
+        }
+    };
+
+    Ok(version)
+}
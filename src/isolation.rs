@@ -0,0 +1,244 @@
+use crate::config::{Config, Isolation};
+use crate::felloe::Felloe;
+use crate::release::is_helm2;
+use log::*;
+use std::{env, fs, path::Path, path::PathBuf};
+
+/// Where a helm version keeps its repositories and plugins.
+#[derive(Debug)]
+pub struct HelmDirs {
+    pub repositories_file: PathBuf,
+    pub plugins_dir: PathBuf,
+}
+
+/// Name of the isolated home shared by `version` under the given mode.
+pub fn home_key(version: &str, isolation: Isolation) -> Option<String> {
+    match isolation {
+        Isolation::None => None,
+        Isolation::Version => Some(version.to_string()),
+        Isolation::Major => Some(version.split('.').next().unwrap_or(version).to_string()),
+    }
+}
+
+pub fn get_homes_path(felloe: &Felloe) -> PathBuf {
+    felloe.home().join("homes")
+}
+
+/// The isolated home for `version`, if isolation is enabled.
+pub fn get_home(felloe: &Felloe, version: &str, config: &Config) -> Option<PathBuf> {
+    home_key(version, config.isolation).map(|key| get_homes_path(felloe).join(key))
+}
+
+/// Environment variables that give helm `version` its isolated directories.
+pub fn helm_env(felloe: &Felloe, version: &str, config: &Config) -> Vec<(&'static str, String)> {
+    let home = match get_home(felloe, version, config) {
+        Some(home) => home,
+        None => return Vec::new(),
+    };
+
+    if is_helm2(version) {
+        vec![("HELM_HOME", path_string(&home.join("helm")))]
+    } else {
+        vec![
+            ("HELM_CONFIG_HOME", path_string(&home.join("config"))),
+            ("HELM_DATA_HOME", path_string(&home.join("data"))),
+            ("HELM_CACHE_HOME", path_string(&home.join("cache"))),
+        ]
+    }
+}
+
+/// Names of every variable `helm_env` may set.
+pub static HELM_ENV_VARS: &[&str] = &[
+    "HELM_HOME",
+    "HELM_CONFIG_HOME",
+    "HELM_DATA_HOME",
+    "HELM_CACHE_HOME",
+];
+
+/// Repository and plugin locations for `version`, honoring isolation and helm's own
+/// environment variables.
+pub fn helm_dirs(felloe: &Felloe, version: &str, config: &Config) -> HelmDirs {
+    let vars = helm_env(felloe, version, config);
+    let var = |name: &str| {
+        vars.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| PathBuf::from(v))
+            .or_else(|| env::var_os(name).map(PathBuf::from))
+    };
+
+    if is_helm2(version) {
+        let home = var("HELM_HOME").unwrap_or_else(|| dirs::home_dir().unwrap().join(".helm"));
+
+        HelmDirs {
+            repositories_file: home.join("repository").join("repositories.yaml"),
+            plugins_dir: home.join("plugins"),
+        }
+    } else {
+        let config_home = var("HELM_CONFIG_HOME").unwrap_or_else(default_config_home);
+        let data_home = var("HELM_DATA_HOME").unwrap_or_else(default_data_home);
+
+        HelmDirs {
+            repositories_file: config_home.join("repositories.yaml"),
+            plugins_dir: data_home.join("plugins"),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn default_config_home() -> PathBuf {
+    dirs::home_dir()
+        .unwrap()
+        .join("Library")
+        .join("Preferences")
+        .join("helm")
+}
+
+#[cfg(target_os = "macos")]
+fn default_data_home() -> PathBuf {
+    dirs::home_dir().unwrap().join("Library").join("helm")
+}
+
+#[cfg(not(target_os = "macos"))]
+fn default_config_home() -> PathBuf {
+    dirs::config_dir().unwrap().join("helm")
+}
+
+#[cfg(not(target_os = "macos"))]
+fn default_data_home() -> PathBuf {
+    dirs::data_dir().unwrap().join("helm")
+}
+
+pub fn path(felloe: &Felloe, version: &str) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+
+    match get_home(felloe, version, &config) {
+        Some(home) => println!("{}", home.display()),
+        None => {
+            let dirs = helm_dirs(felloe, version, &config);
+            println!(
+                "Isolation is disabled. helm {} uses {} and {}",
+                version,
+                dirs.repositories_file.display(),
+                dirs.plugins_dir.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy repositories and/or plugins from one version's directories to another's.
+pub fn copy(
+    felloe: &Felloe,
+    from: &str,
+    to: &str,
+    repositories: bool,
+    plugins: bool,
+    force: bool,
+) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    let source = helm_dirs(felloe, from, &config);
+    let target = helm_dirs(felloe, to, &config);
+
+    if repositories {
+        copy_repositories(&source, &target, force)?;
+    }
+
+    if plugins {
+        copy_plugins(&source, &target, force)?;
+    }
+
+    Ok(())
+}
+
+fn copy_repositories(
+    source: &HelmDirs,
+    target: &HelmDirs,
+    force: bool,
+) -> Result<(), failure::Error> {
+    if !source.repositories_file.exists() {
+        println!(
+            "No repositories to copy from {}",
+            source.repositories_file.display()
+        );
+        return Ok(());
+    }
+
+    if target.repositories_file.exists() && !force {
+        return Err(failure::err_msg(format!(
+            "{} already exists. Use --force to overwrite it",
+            target.repositories_file.display()
+        )));
+    }
+
+    fs::create_dir_all(target.repositories_file.parent().unwrap())?;
+    fs::copy(&source.repositories_file, &target.repositories_file)?;
+
+    println!(
+        "Copied repositories to {}",
+        target.repositories_file.display()
+    );
+    Ok(())
+}
+
+fn copy_plugins(source: &HelmDirs, target: &HelmDirs, force: bool) -> Result<(), failure::Error> {
+    if !source.plugins_dir.exists() {
+        println!("No plugins to copy from {}", source.plugins_dir.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&target.plugins_dir)?;
+
+    for entry in source.plugins_dir.read_dir()? {
+        let entry = entry?;
+        let destination = target.plugins_dir.join(entry.file_name());
+
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        if let Ok(metadata) = fs::symlink_metadata(&destination) {
+            if !force {
+                println!(
+                    "Plugin {} already exists, skipping",
+                    entry.file_name().to_string_lossy()
+                );
+                continue;
+            }
+
+            if metadata.file_type().is_symlink() {
+                fs::remove_file(&destination)?;
+            } else {
+                fs::remove_dir_all(&destination)?;
+            }
+        }
+
+        info!("Copying plugin {:?} to {:?}", entry.path(), destination);
+        copy_dir(&entry.path(), &destination)?;
+        println!("Copied plugin {}", entry.file_name().to_string_lossy());
+    }
+
+    Ok(())
+}
+
+/// Recursively copy a directory, following symbolic links.
+pub fn copy_dir(from: &Path, to: &Path) -> Result<(), failure::Error> {
+    fs::create_dir_all(to)?;
+
+    for entry in from.read_dir()? {
+        let path = entry?.path();
+        let destination = to.join(path.file_name().unwrap());
+
+        if path.is_dir() {
+            copy_dir(&path, &destination)?;
+        } else {
+            fs::copy(&path, &destination)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
@@ -0,0 +1,195 @@
+use crate::config::PickerKeys;
+use crossterm::input::KeyEvent;
+
+/// Something the interactive picker can do on a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Search,
+    Select,
+    Mark,
+    Remove,
+    Download,
+    Remote,
+    Quit,
+}
+
+/// Every action with its name in the config file and its default keys.
+static DEFAULT_BINDINGS: &[(Action, &str, &[&str])] = &[
+    (Action::Up, "up", &["up", "w", "k"]),
+    (Action::Down, "down", &["down", "s", "j"]),
+    (Action::PageUp, "page-up", &["page-up"]),
+    (Action::PageDown, "page-down", &["page-down"]),
+    (Action::Top, "top", &["home", "g"]),
+    (Action::Bottom, "bottom", &["end", "G"]),
+    (Action::Search, "search", &["/"]),
+    (Action::Select, "select", &["enter", "i"]),
+    (Action::Mark, "mark", &["space"]),
+    (Action::Remove, "remove", &["d", "delete", "backspace"]),
+    (Action::Download, "download", &["D"]),
+    (Action::Remote, "remote", &["r"]),
+    (Action::Quit, "quit", &["q"]),
+];
+
+/// The keys bound to each picker action.
+#[derive(Debug)]
+pub struct KeyMap {
+    bindings: Vec<(KeyEvent, Action)>,
+}
+
+impl KeyMap {
+    /// The default bindings, with the actions set in the config file bound to their keys instead.
+    pub fn load(keys: &PickerKeys) -> Result<KeyMap, failure::Error> {
+        let mut bindings: Vec<(KeyEvent, Action)> = Vec::new();
+
+        for (action, name, defaults) in DEFAULT_BINDINGS {
+            let names: Vec<&str> = match configured(keys, *action) {
+                Some(names) => names.iter().map(String::as_str).collect(),
+                None => defaults.to_vec(),
+            };
+
+            for key_name in names {
+                let key = parse_key(key_name)?;
+
+                if let Some((_, bound)) = bindings.iter().find(|(k, _)| *k == key) {
+                    return Err(failure::err_msg(format!(
+                        "Key {} is bound to both {} and {}",
+                        key_name,
+                        action_name(*bound),
+                        name
+                    )));
+                }
+
+                bindings.push((key, *action));
+            }
+        }
+
+        Ok(KeyMap { bindings })
+    }
+
+    pub fn action(&self, key: KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, action)| *action)
+    }
+
+    /// The first key bound to `action`, for key hints.
+    pub fn key(&self, action: Action) -> Option<KeyEvent> {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(key, _)| *key)
+    }
+}
+
+fn configured(keys: &PickerKeys, action: Action) -> Option<&Vec<String>> {
+    match action {
+        Action::Up => keys.up.as_ref(),
+        Action::Down => keys.down.as_ref(),
+        Action::PageUp => keys.page_up.as_ref(),
+        Action::PageDown => keys.page_down.as_ref(),
+        Action::Top => keys.top.as_ref(),
+        Action::Bottom => keys.bottom.as_ref(),
+        Action::Search => keys.search.as_ref(),
+        Action::Select => keys.select.as_ref(),
+        Action::Mark => keys.mark.as_ref(),
+        Action::Remove => keys.remove.as_ref(),
+        Action::Download => keys.download.as_ref(),
+        Action::Remote => keys.remote.as_ref(),
+        Action::Quit => keys.quit.as_ref(),
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    DEFAULT_BINDINGS
+        .iter()
+        .find(|(a, _, _)| *a == action)
+        .map(|(_, name, _)| *name)
+        .unwrap_or_default()
+}
+
+/// Parse a key name: a single character such as `k` or `G`, a named key such as `page-down`,
+/// `f5` or `space`, or a character with a modifier such as `ctrl-d` or `alt-x`.
+pub fn parse_key(name: &str) -> Result<KeyEvent, failure::Error> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyEvent::Char(c));
+    }
+
+    let lower = name.to_lowercase();
+    let modified = |prefix: &str| {
+        let mut chars = lower.strip_prefix(prefix)?.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(c) = modified("ctrl-") {
+        return Ok(KeyEvent::Ctrl(c));
+    }
+    if let Some(c) = modified("alt-") {
+        return Ok(KeyEvent::Alt(c));
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+        return Ok(KeyEvent::F(n));
+    }
+
+    Ok(match lower.as_str() {
+        "up" => KeyEvent::Up,
+        "down" => KeyEvent::Down,
+        "left" => KeyEvent::Left,
+        "right" => KeyEvent::Right,
+        "home" => KeyEvent::Home,
+        "end" => KeyEvent::End,
+        "page-up" | "pageup" => KeyEvent::PageUp,
+        "page-down" | "pagedown" => KeyEvent::PageDown,
+        "enter" => KeyEvent::Enter,
+        "tab" => KeyEvent::Tab,
+        "backspace" => KeyEvent::Backspace,
+        "delete" => KeyEvent::Delete,
+        "insert" => KeyEvent::Insert,
+        "esc" => KeyEvent::Esc,
+        "space" => KeyEvent::Char(' '),
+        _ => {
+            return Err(failure::err_msg(format!(
+                "Unknown key {}. Expected a character or a key such as up, page-down, enter, space or ctrl-d",
+                name
+            )))
+        }
+    })
+}
+
+/// How `key` is shown in key hints, with arrows spelled out when `ascii` is set.
+pub fn key_name(key: KeyEvent, ascii: bool) -> String {
+    match key {
+        KeyEvent::Up if !ascii => "↑".to_string(),
+        KeyEvent::Down if !ascii => "↓".to_string(),
+        KeyEvent::Up => "up".to_string(),
+        KeyEvent::Down => "down".to_string(),
+        KeyEvent::Left => "left".to_string(),
+        KeyEvent::Right => "right".to_string(),
+        KeyEvent::Home => "home".to_string(),
+        KeyEvent::End => "end".to_string(),
+        KeyEvent::PageUp => "pgup".to_string(),
+        KeyEvent::PageDown => "pgdn".to_string(),
+        KeyEvent::Enter => "enter".to_string(),
+        KeyEvent::Tab => "tab".to_string(),
+        KeyEvent::Backspace => "backspace".to_string(),
+        KeyEvent::Delete => "delete".to_string(),
+        KeyEvent::Insert => "insert".to_string(),
+        KeyEvent::Esc => "esc".to_string(),
+        KeyEvent::Char(' ') => "space".to_string(),
+        KeyEvent::Char(c) => c.to_string(),
+        KeyEvent::Ctrl(c) => format!("ctrl-{}", c),
+        KeyEvent::Alt(c) => format!("alt-{}", c),
+        KeyEvent::F(n) => format!("f{}", n),
+        _ => "?".to_string(),
+    }
+}
@@ -1,7 +1,34 @@
+pub mod archives;
 pub mod cli;
 pub mod commands;
+pub mod config;
+pub mod confirm;
 pub mod constants;
+pub mod du;
+pub mod felloe;
+pub mod isolation;
+pub mod keymap;
+pub mod lock;
+pub mod metadata;
+pub mod migrate;
+pub mod outdated;
+pub mod picker;
+pub mod platform;
+pub mod plugins;
 pub mod progress;
+pub mod project;
+pub mod prune;
 pub mod release;
+pub mod repos;
+pub mod shell;
+pub mod source;
+pub mod theme;
+pub mod tiller;
+pub mod trash;
+pub mod upgrade;
 
-pub use cli::{Cli, Command};
+pub use cli::{
+    CacheCommand, Cli, Command, ConfirmOpts, HomesCommand, PlatformOpts, PluginsCommand,
+    ReposCommand, TillerCommand,
+};
+pub use felloe::Felloe;
@@ -0,0 +1,287 @@
+use crate::archives::validate_digest;
+use crate::commands::{after_activate, fetch_parallel, plural, report_fetched, Fetched};
+use crate::config::PluginSpec;
+use crate::constants::LOCK_FILE_NAME;
+use crate::felloe::Felloe;
+use crate::metadata::VersionMetadata;
+use crate::platform::Platform;
+use crate::plugins;
+use crate::project::{find_lock_file, find_version_file, read_version_file, remember_project};
+use crate::release::Release;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::Path, time::Instant};
+
+static LOCK_FILE_HEADER: &str =
+    "# Generated by `felloe lock`. Install what it pins with `felloe sync`.\n\n";
+
+/// A release archive for one platform, pinned by its digest.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LockedPlatform {
+    pub os: String,
+    pub arch: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl LockedPlatform {
+    pub fn platform(&self) -> Result<Platform, failure::Error> {
+        Platform::new(Some(self.os.clone()), Some(self.arch.clone()))
+    }
+}
+
+/// Contents of a `felloe.lock` file.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Lockfile {
+    pub version: String,
+    #[serde(default, rename = "platform", skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<LockedPlatform>,
+    #[serde(default, rename = "plugin", skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginSpec>,
+}
+
+impl Lockfile {
+    pub fn read(path: &Path) -> Result<Lockfile, failure::Error> {
+        debug!("Reading lock file {:?}", path);
+        let content = fs::read_to_string(path)?;
+
+        let lockfile: Lockfile = toml::from_str(&content)
+            .map_err(|e| failure::err_msg(format!("Invalid {}: {}", path.display(), e)))?;
+
+        for entry in &lockfile.platforms {
+            validate_digest(&entry.sha256).map_err(|e| {
+                failure::err_msg(format!(
+                    "Invalid {}: {}-{}: {}",
+                    path.display(),
+                    entry.os,
+                    entry.arch,
+                    e
+                ))
+            })?;
+        }
+
+        Ok(lockfile)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), failure::Error> {
+        let content = toml::to_string(self)?;
+        fs::write(path, format!("{}{}", LOCK_FILE_HEADER, content))?;
+
+        Ok(())
+    }
+
+    /// The locked archive for `platform`, if there is one.
+    pub fn platform(&self, platform: &Platform) -> Option<&LockedPlatform> {
+        self.platforms
+            .iter()
+            .find(|p| p.os == platform.os && p.arch == platform.arch)
+    }
+}
+
+/// Write `felloe.lock` next to the nearest `.helm-version` file, pinning digests for the host,
+/// every platform already in the lock file and `platforms`.
+pub fn lock(felloe: &Felloe, platforms: Vec<Platform>) -> Result<(), failure::Error> {
+    let cwd = env::current_dir()?;
+    let version_file = find_version_file(&cwd).ok_or_else(|| {
+        failure::err_msg(format!(
+            "No .helm-version file found in {} or its parents",
+            cwd.display()
+        ))
+    })?;
+    let path = version_file.parent().unwrap().join(LOCK_FILE_NAME);
+
+    let mut version = read_version_file(&version_file)?;
+    remember_project(felloe, &version_file);
+    if version == "latest" {
+        version = felloe.fetch_release("latest")?.tag_name;
+    }
+
+    let previous = if path.exists() {
+        Some(Lockfile::read(&path)?)
+    } else {
+        None
+    };
+
+    let mut targets = vec![Platform::current()];
+    if let Some(previous) = &previous {
+        for entry in &previous.platforms {
+            targets.push(entry.platform()?);
+        }
+    }
+    targets.extend(platforms);
+    targets.sort_by_key(|p| p.to_string());
+    targets.dedup();
+
+    let mut locked = Vec::new();
+    for platform in targets {
+        // Keep digests that are already locked rather than trusting the remote again
+        let existing = previous
+            .as_ref()
+            .filter(|l| l.version == version)
+            .and_then(|l| l.platform(&platform));
+
+        let entry = match existing {
+            Some(entry) => entry.clone(),
+            None => {
+                info!("Fetching digest of helm {} for {}", version, platform);
+                let archive = felloe.archive(&Release::from_tag(&version), &platform)?;
+                LockedPlatform {
+                    url: archive.url,
+                    sha256: archive.sha256,
+                    os: platform.os,
+                    arch: platform.arch,
+                }
+            }
+        };
+
+        locked.push(entry);
+    }
+
+    let lockfile = Lockfile {
+        plugins: locked_plugins(felloe, &version)?,
+        platforms: locked,
+        version,
+    };
+    lockfile.write(&path)?;
+
+    println!(
+        "Locked helm {} for {} in {}",
+        lockfile.version,
+        plural(lockfile.platforms.len(), "platform"),
+        path.display()
+    );
+    Ok(())
+}
+
+/// The configured plugins, with unpinned ones pinned to the version installed for helm `version`.
+fn locked_plugins(felloe: &Felloe, version: &str) -> Result<Vec<PluginSpec>, failure::Error> {
+    let config = felloe.config()?;
+    let installed = plugins::installed(felloe, version, &config)?;
+
+    Ok(config
+        .plugins
+        .iter()
+        .map(|spec| {
+            let mut spec = spec.clone();
+
+            if spec.version.is_none() {
+                spec.version = installed
+                    .iter()
+                    .find(|p| p.name == spec.name)
+                    .map(|p| p.version.clone())
+                    .filter(|v| !v.is_empty());
+            }
+
+            if spec.version.is_none() {
+                warn!(
+                    "Plugin {} is not pinned to a version. Pin it in {} or install it for helm {} before locking",
+                    spec.name,
+                    felloe.config_path().display(),
+                    version
+                );
+            }
+
+            spec
+        })
+        .collect())
+}
+
+/// Install and activate the helm version and plugins pinned by the nearest `felloe.lock`,
+/// verifying each archive against its locked digest.
+pub fn sync(felloe: &Felloe, all_platforms: bool, jobs: usize) -> Result<(), failure::Error> {
+    let cwd = env::current_dir()?;
+    let path = find_lock_file(&cwd).ok_or_else(|| {
+        failure::err_msg(format!(
+            "No {} found in {} or its parents. Run `felloe lock` to create one",
+            LOCK_FILE_NAME,
+            cwd.display()
+        ))
+    })?;
+    let lockfile = Lockfile::read(&path)?;
+
+    if let Some(version_file) = find_version_file(&cwd) {
+        let pinned = read_version_file(&version_file)?;
+        remember_project(felloe, &version_file);
+
+        if pinned != "latest" && pinned != lockfile.version {
+            return Err(failure::err_msg(format!(
+                "{} pins helm {} but {} locks {}. Run `felloe lock` to update it",
+                version_file.display(),
+                pinned,
+                path.display(),
+                lockfile.version
+            )));
+        }
+    }
+
+    let current = Platform::current();
+    let targets = if all_platforms {
+        lockfile.platforms.clone()
+    } else {
+        match lockfile.platform(&current) {
+            Some(entry) => vec![entry.clone()],
+            None => {
+                return Err(failure::err_msg(format!(
+                    "{} has no digest for {}. Run `felloe lock --platform {}` to add it",
+                    path.display(),
+                    current,
+                    current
+                )))
+            }
+        }
+    };
+
+    let started = Instant::now();
+    let version = lockfile.version.clone();
+    let items = targets
+        .into_iter()
+        .map(|entry| (format!("{} {}-{}", version, entry.os, entry.arch), entry))
+        .collect();
+
+    let worker = felloe.clone();
+    let results = fetch_parallel(
+        items,
+        jobs,
+        felloe.reporter(),
+        move |entry: &LockedPlatform, task| {
+            let platform = entry.platform()?;
+            let label = format!("{} {}", version, platform);
+
+            // Versions unpacked from another archive, or before digests were recorded, are
+            // unpacked again from the locked one
+            let metadata = VersionMetadata::load_from(&worker.cache_path(&version));
+            let unpacked_from = metadata.digests.get(&platform.to_string());
+
+            if worker.is_installed(&version, &platform) && unpacked_from == Some(&entry.sha256) {
+                task.finish(&format!("helm {} already cached", label));
+                return Ok((label, Fetched::Cached));
+            }
+
+            let fetched =
+                worker.download_verified(&version, &platform, &entry.url, &entry.sha256, task)?;
+            Ok((label, fetched))
+        },
+    )?;
+
+    let summary = format!(
+        "{} of helm {}",
+        plural(results.len(), "platform"),
+        lockfile.version
+    );
+    report_fetched(&results, &summary, started)?;
+
+    if lockfile.platform(&current).is_none() {
+        return Ok(());
+    }
+
+    let activated = felloe.activate(&lockfile.version)?;
+    println!("Activated helm {}", lockfile.version);
+    after_activate(felloe, activated.previous, &lockfile.version);
+
+    if !lockfile.plugins.is_empty() {
+        let config = felloe.config()?;
+        plugins::ensure(felloe, &lockfile.version, &lockfile.plugins, &config)?;
+    }
+
+    Ok(())
+}
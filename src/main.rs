@@ -1,6 +1,20 @@
 use env_logger::Builder;
 use exitfailure::ExitFailure;
-use felloe::{commands as cmd, Cli, Command};
+use felloe::{
+    archives, commands as cmd,
+    confirm::Confirm,
+    du, isolation, lock,
+    migrate::{self, MigrateOptions},
+    outdated, picker,
+    platform::Platform,
+    plugins,
+    progress::{ProgressMode, Reporter},
+    project::normalize_version,
+    prune::{self, PruneOptions},
+    repos, tiller, trash,
+    upgrade::{self, UpgradeScope},
+    CacheCommand, Cli, Command, Felloe, HomesCommand, PluginsCommand, ReposCommand, TillerCommand,
+};
 use log::Level;
 use structopt::StructOpt;
 
@@ -12,8 +26,30 @@ fn main() -> Result<(), ExitFailure> {
         .filter(Some("felloe"), log_level.to_level_filter())
         .try_init()?;
 
+    let mode = if args.quiet {
+        ProgressMode::None
+    } else if let Some(mode) = &args.progress {
+        mode.parse()?
+    } else {
+        ProgressMode::detect()
+    };
+    let felloe = Felloe::from_env()?.with_reporter(Reporter::new(mode));
+
+    // Commands whose output is consumed by shells or scripts never print a release notice
+    let notify = !matches!(
+        args.cmd,
+        Some(Command::Completions { .. })
+            | Some(Command::Env { .. })
+            | Some(Command::Shell { .. })
+            | Some(Command::Outdated { .. })
+    );
+
     if let Some(cmd) = args.cmd {
         match cmd {
+            Command::Cache { cmd } => match cmd {
+                CacheCommand::Ls => archives::ls(&felloe),
+                CacheCommand::Clean { uninstalled } => archives::clean(&felloe, uninstalled),
+            },
             Command::Completions { shell } => {
                 let mut bytes = Vec::<u8>::new();
                 Cli::clap().gen_completions_to("felloe", shell, &mut bytes);
@@ -22,27 +58,185 @@ fn main() -> Result<(), ExitFailure> {
                 println!("{}", output);
                 Ok(())
             }
-            Command::Exec { version, args } => cmd::exec(version, args),
-            Command::Latest => cmd::install_latest(),
-            Command::List => cmd::list(),
-            Command::Prune => cmd::prune(),
-            Command::Remove { versions, force } => cmd::remove(versions, force),
-            Command::Run { version, args } => cmd::run_helm(&version, args),
-            Command::Uninstall => cmd::uninstall(),
+            Command::Download {
+                versions,
+                platform,
+                jobs,
+            } => cmd::download_versions(
+                &felloe,
+                versions,
+                &Platform::new(platform.os, platform.arch)?,
+                jobs,
+            ),
+            Command::Du { unused_for } => du::du(&felloe, &unused_for),
+            Command::Env { shell } => cmd::env(shell),
+            Command::Exec { version, args } => cmd::exec(&felloe, version, args),
+            Command::Homes { cmd } => match cmd {
+                HomesCommand::Path { version } => {
+                    isolation::path(&felloe, &felloe.resolve(version.as_deref())?)
+                }
+                HomesCommand::Copy {
+                    from,
+                    to,
+                    repositories,
+                    plugins,
+                    force,
+                } => isolation::copy(
+                    &felloe,
+                    &normalize_version(&from),
+                    &normalize_version(&to),
+                    repositories || !plugins,
+                    plugins || !repositories,
+                    force,
+                ),
+            },
+            Command::Install { version, platform } => cmd::install(
+                &felloe,
+                &version,
+                &Platform::new(platform.os, platform.arch)?,
+            ),
+            Command::Latest => cmd::install_latest(&felloe),
+            Command::List { long } => cmd::list(&felloe, long),
+            Command::Lock { platforms } => lock::lock(
+                &felloe,
+                platforms
+                    .iter()
+                    .map(|p| p.parse())
+                    .collect::<Result<_, _>>()?,
+            ),
+            Command::Migrate {
+                v2,
+                v3,
+                dry_run,
+                releases,
+                skip_cleanup,
+                yes,
+            } => migrate::migrate(
+                &felloe,
+                &v2,
+                &v3,
+                &MigrateOptions {
+                    dry_run,
+                    releases,
+                    skip_cleanup,
+                    yes,
+                },
+            ),
+            Command::Outdated { refresh_index } => outdated::outdated(&felloe, refresh_index),
+            Command::Plugins { cmd } => match cmd {
+                PluginsCommand::List { version } => {
+                    plugins::list(&felloe, &felloe.resolve(version.as_deref())?)
+                }
+                PluginsCommand::Install { version } => {
+                    plugins::ensure_configured(&felloe, &felloe.resolve(version.as_deref())?)
+                }
+                PluginsCommand::Sync { from, to } => {
+                    plugins::sync(&felloe, &normalize_version(&from), &normalize_version(&to))
+                }
+            },
+            Command::Prune {
+                keep_latest,
+                keep_per_minor,
+                older_than,
+                unused_for,
+                keep,
+                confirm,
+            } => prune::prune(
+                &felloe,
+                &PruneOptions {
+                    keep_latest,
+                    keep_per_minor,
+                    older_than: older_than.map(|age| prune::parse_age(&age)).transpose()?,
+                    unused_for: unused_for.map(|age| prune::parse_age(&age)).transpose()?,
+                    keep,
+                    confirm: Confirm::from(confirm),
+                },
+            ),
+            Command::Remove {
+                versions,
+                force,
+                confirm,
+            } => {
+                felloe.check_removal(&versions, force)?;
+
+                if Confirm::from(confirm).removal(&felloe, &versions)? {
+                    felloe.remove(&versions, force)
+                } else {
+                    Ok(())
+                }
+            }
+            Command::Repos { cmd } => match cmd {
+                ReposCommand::List { version } => {
+                    repos::list(&felloe, &felloe.resolve(version.as_deref())?)
+                }
+                ReposCommand::Sync { from, to, update } => repos::sync(
+                    &felloe,
+                    &normalize_version(&from),
+                    &normalize_version(&to),
+                    update,
+                ),
+            },
+            Command::Run { version, args } => cmd::run(&felloe, &version, args),
+            Command::Shell {
+                version,
+                shell,
+                auto,
+                unset,
+            } => cmd::shell(&felloe, version, shell, auto, unset),
+            Command::Sync {
+                all_platforms,
+                jobs,
+            } => lock::sync(&felloe, all_platforms, jobs),
+            Command::Tiller { cmd } => match cmd {
+                TillerCommand::Start {
+                    version,
+                    listen,
+                    storage,
+                    namespace,
+                } => tiller::start(&felloe, version, &listen, &storage, &namespace),
+                TillerCommand::Stop { version } => tiller::stop(&felloe, version),
+                TillerCommand::Status { version } => tiller::status(&felloe, version),
+            },
+            Command::Restore { version } => trash::restore(&felloe, version),
+            Command::Uninstall { confirm } => cmd::uninstall(&felloe, &Confirm::from(confirm)),
+            Command::Upgrade {
+                patch: _,
+                minor,
+                major,
+                dry_run,
+                prune,
+            } => {
+                let scope = if major {
+                    UpgradeScope::Major
+                } else if minor {
+                    UpgradeScope::Minor
+                } else {
+                    UpgradeScope::Patch
+                };
+                upgrade::upgrade(&felloe, scope, dry_run, prune)
+            }
             Command::Versions {
                 filter,
                 prerelease,
                 last,
-            } => cmd::versions(filter, prerelease, last),
-            Command::Which { version } => cmd::which(version),
+            } => cmd::versions(&felloe, filter, prerelease, last),
+            Command::Which { version, platform } => cmd::which(
+                &felloe,
+                version,
+                &Platform::new(platform.os, platform.arch)?,
+            ),
         }?
     } else if args.latest {
-        let release = cmd::fetch_release("latest")?;
+        let release = felloe.fetch_release("latest")?;
         println!("{}", release.tag_name);
     } else if let Some(version) = args.version {
-        cmd::install(&version)?
+        cmd::install(&felloe, &version, &Platform::current())?
     } else {
-        cmd::select_version()?
+        picker::select_version(&felloe)?
+    }
+
+    if notify {
+        outdated::notify(&felloe);
     }
 
     Ok(())
@@ -0,0 +1,124 @@
+use crate::constants::METADATA_FILE_NAME;
+use crate::platform::Platform;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// What felloe knows about a downloaded version, kept next to its binaries.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct VersionMetadata {
+    /// Unix time the version was downloaded
+    pub downloaded_at: Option<u64>,
+    /// Unix time the version was last activated or run through felloe
+    pub last_used: Option<u64>,
+    /// sha256 digest of the release archive each platform was unpacked from
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub digests: BTreeMap<String, String>,
+}
+
+impl VersionMetadata {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(METADATA_FILE_NAME)
+    }
+
+    /// Load the metadata kept in the version directory `dir`, which is empty for versions
+    /// downloaded before it existed.
+    pub fn load_from(dir: &Path) -> VersionMetadata {
+        fs::read_to_string(VersionMetadata::path(dir))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), failure::Error> {
+        fs::write(VersionMetadata::path(dir), serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// When the version was downloaded, falling back to the modification time of its directory
+    /// `dir`.
+    pub fn downloaded_at(&self, dir: &Path) -> Option<SystemTime> {
+        self.downloaded_at
+            .map(from_unix)
+            .or_else(|| fs::metadata(dir).ok()?.modified().ok())
+    }
+
+    /// When the version was last used, or downloaded if it has never been used.
+    pub fn last_used(&self, dir: &Path) -> Option<SystemTime> {
+        self.last_used
+            .map(from_unix)
+            .or_else(|| self.downloaded_at(dir))
+    }
+}
+
+/// Record that the version in directory `dir` was just unpacked for `platform` from the archive
+/// with digest `sha`.
+pub fn record_downloaded(dir: &Path, platform: &Platform, sha: &str) {
+    update(dir, |metadata, now| {
+        metadata.downloaded_at = Some(now);
+        metadata
+            .digests
+            .insert(platform.to_string(), sha.to_string());
+    });
+}
+
+/// Record that the version in directory `dir` was just activated or run.
+pub fn record_used(dir: &Path) {
+    update(dir, |metadata, now| metadata.last_used = Some(now));
+}
+
+fn update(dir: &Path, f: impl FnOnce(&mut VersionMetadata, u64)) {
+    if !dir.exists() {
+        return;
+    }
+
+    let mut metadata = VersionMetadata::load_from(dir);
+
+    // Writing the file touches the version directory, so keep its time as the download time
+    if metadata.downloaded_at.is_none() {
+        metadata.downloaded_at = fs::metadata(dir)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+    }
+
+    f(&mut metadata, now());
+
+    if let Err(e) = metadata.save(dir) {
+        debug!("Unable to save metadata in {:?}: {}", dir, e);
+    }
+}
+
+/// How long ago `time` was, if it is in the past.
+pub fn age(time: Option<SystemTime>) -> Option<Duration> {
+    time.and_then(|t| SystemTime::now().duration_since(t).ok())
+}
+
+/// How long ago `time` was, for display.
+pub fn days_ago(time: Option<SystemTime>) -> String {
+    match age(time).map(|age| age.as_secs() / (24 * 60 * 60)) {
+        Some(0) => "today".to_string(),
+        Some(1) => "yesterday".to_string(),
+        Some(days) => format!("{} days ago", days),
+        None => "unknown".to_string(),
+    }
+}
+
+fn from_unix(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
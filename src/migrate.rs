@@ -0,0 +1,172 @@
+use crate::commands::{helm_output, run_helm};
+use crate::confirm::ask;
+use crate::constants::HELM_2TO3_PLUGIN_URL;
+use crate::felloe::Felloe;
+use crate::isolation;
+use crate::platform::Platform;
+use crate::project::normalize_version;
+use crate::release::is_helm2;
+use crate::tiller;
+use log::*;
+use std::env;
+
+/// Options controlling how `felloe migrate` walks through the helm-2to3 steps.
+#[derive(Debug, Default)]
+pub struct MigrateOptions {
+    pub dry_run: bool,
+    pub releases: Vec<String>,
+    pub skip_cleanup: bool,
+    pub yes: bool,
+}
+
+pub fn migrate(
+    felloe: &Felloe,
+    v2: &str,
+    v3: &str,
+    opts: &MigrateOptions,
+) -> Result<(), failure::Error> {
+    let v2 = normalize_version(v2);
+    let v3 = normalize_version(v3);
+
+    if !is_helm2(&v2) || is_helm2(&v3) {
+        return Err(failure::err_msg(format!(
+            "Expected a helm 2 and a helm 3 version, got {} and {}",
+            v2, v3
+        )));
+    }
+
+    for version in [&v2, &v3].iter() {
+        if !felloe.is_installed(version, &Platform::current()) {
+            return Err(failure::err_msg(format!(
+                "helm {} is not installed. Run `felloe download {}` first",
+                version, version
+            )));
+        }
+    }
+
+    if let Some(home) = isolation::get_home(felloe, &v2, &felloe.config()?) {
+        debug!("Using isolated helm 2 home {:?}", home);
+        env::set_var("HELM_V2_HOME", home.join("helm"));
+    }
+
+    install_plugin(felloe, &v3)?;
+
+    let dry_run = if opts.dry_run {
+        vec!["--dry-run".to_string()]
+    } else {
+        vec![]
+    };
+    let release_flags = [tiller_args(felloe, &v2), dry_run.clone()].concat();
+
+    step(opts, "Move helm 2 configuration to helm 3", || {
+        run_helm(felloe, &v3, plugin_args(&["move", "config"], &dry_run))
+    })?;
+
+    let releases = if opts.releases.is_empty() {
+        list_releases(felloe, &v2)?
+    } else {
+        opts.releases.clone()
+    };
+
+    if releases.is_empty() {
+        println!("No helm 2 releases found to convert");
+    }
+
+    for release in releases.iter() {
+        step(opts, &format!("Convert release {}", release), || {
+            run_helm(
+                felloe,
+                &v3,
+                plugin_args(&["convert", release], &release_flags),
+            )
+        })?;
+    }
+
+    if opts.skip_cleanup {
+        println!("Skipping cleanup of helm 2 configuration and release data");
+    } else {
+        step(
+            opts,
+            "Clean up helm 2 configuration, release data and tiller",
+            || run_helm(felloe, &v3, plugin_args(&["cleanup"], &release_flags)),
+        )?;
+    }
+
+    if opts.dry_run {
+        println!("Dry run complete. Nothing was changed.");
+    } else {
+        println!("Migrated from helm {} to helm {}", v2, v3);
+    }
+
+    Ok(())
+}
+
+fn plugin_args(command: &[&str], flags: &[String]) -> Vec<String> {
+    std::iter::once("2to3")
+        .chain(command.iter().cloned())
+        .map(String::from)
+        .chain(flags.iter().cloned())
+        .collect()
+}
+
+fn install_plugin(felloe: &Felloe, v3: &str) -> Result<(), failure::Error> {
+    let plugins = helm_output(felloe, v3, ["plugin", "list"])?;
+
+    if plugins.lines().skip(1).any(|l| l.starts_with("2to3")) {
+        debug!("helm-2to3 plugin is already installed for {}", v3);
+        return Ok(());
+    }
+
+    println!("Installing the helm-2to3 plugin for helm {}", v3);
+    run_helm(felloe, v3, ["plugin", "install", HELM_2TO3_PLUGIN_URL])
+}
+
+/// Releases known to helm 2, which requires a reachable tiller.
+fn list_releases(felloe: &Felloe, v2: &str) -> Result<Vec<String>, failure::Error> {
+    let output = helm_output(felloe, v2, ["list", "--all", "--short"]).map_err(|e| {
+        failure::err_msg(format!(
+            "Unable to list helm 2 releases ({}). Start a local tiller with `felloe tiller start {}` or name releases with --release",
+            e, v2
+        ))
+    })?;
+
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Point helm-2to3 at release data kept by a local tiller started by felloe.
+fn tiller_args(felloe: &Felloe, v2: &str) -> Vec<String> {
+    match tiller::running(felloe) {
+        Ok(Some((_, state))) if state.version == v2 => {
+            let storage = match state.storage.as_str() {
+                "configmap" => "configmaps",
+                _ => "secrets",
+            };
+
+            vec![
+                "--tiller-out-cluster".to_string(),
+                format!("--release-storage={}", storage),
+                format!("--tiller-ns={}", state.namespace),
+            ]
+        }
+        _ => vec![],
+    }
+}
+
+fn step<F>(opts: &MigrateOptions, description: &str, f: F) -> Result<(), failure::Error>
+where
+    F: FnOnce() -> Result<(), failure::Error>,
+{
+    println!("==> {}", description);
+
+    if !opts.yes && !opts.dry_run && !ask("Continue?")? {
+        println!("Skipped");
+        return Ok(());
+    }
+
+    f()
+}
@@ -0,0 +1,200 @@
+use crate::constants::{RELEASE_INDEX_FILE, RELEASE_INDEX_SIZE};
+use crate::felloe::Felloe;
+use crate::metadata::now;
+use crate::release::Version;
+use crate::upgrade::{newest_in, UpgradeScope};
+use atty::Stream;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// Recent release tags cached in `~/.felloe/releases.json`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReleaseIndex {
+    /// Unix time the index was last refreshed
+    pub checked_at: u64,
+    /// Unix time a new release notice was last printed
+    pub notified_at: u64,
+    pub releases: Vec<String>,
+}
+
+impl ReleaseIndex {
+    pub fn path(felloe: &Felloe) -> PathBuf {
+        felloe.home().join(RELEASE_INDEX_FILE)
+    }
+
+    /// Load the cached index, falling back to an empty one when it is missing or unreadable.
+    pub fn load(felloe: &Felloe) -> ReleaseIndex {
+        let path = ReleaseIndex::path(felloe);
+
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable release index at {:?}", path);
+                ReleaseIndex::default()
+            })
+    }
+
+    pub fn save(&self, felloe: &Felloe) -> Result<(), failure::Error> {
+        fs::create_dir_all(felloe.home())?;
+        fs::write(ReleaseIndex::path(felloe), serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Fetch the latest releases and store them in the index.
+    pub fn refresh(felloe: &Felloe) -> Result<ReleaseIndex, failure::Error> {
+        let releases = felloe.fetch_releases(RELEASE_INDEX_SIZE, false)?;
+
+        let index = ReleaseIndex {
+            checked_at: now(),
+            notified_at: ReleaseIndex::load(felloe).notified_at,
+            releases: releases.0.into_iter().map(|r| r.tag_name).collect(),
+        };
+        index.save(felloe)?;
+
+        Ok(index)
+    }
+
+    pub fn versions(&self) -> Vec<Version> {
+        self.releases
+            .iter()
+            .filter_map(|tag| Version::parse(tag))
+            .collect()
+    }
+}
+
+/// Show the newest patch, minor and major release for every downloaded version.
+pub fn outdated(felloe: &Felloe, refresh_index: bool) -> Result<(), failure::Error> {
+    if refresh_index {
+        ReleaseIndex::refresh(felloe)?;
+        return Ok(());
+    }
+
+    let index = match ReleaseIndex::refresh(felloe) {
+        Ok(index) => index,
+        Err(e) => {
+            let index = ReleaseIndex::load(felloe);
+            if index.releases.is_empty() {
+                return Err(e);
+            }
+
+            warn!(
+                "Unable to fetch releases, using the cached release index: {}",
+                e
+            );
+            index
+        }
+    };
+
+    let releases = index.versions();
+    let active = felloe.active().unwrap_or_default();
+
+    let mut installed = felloe.installed_versions().unwrap_or_default();
+    if let Some(active) = &active {
+        if !installed.contains(active) {
+            installed.push(active.clone());
+        }
+    }
+
+    let mut versions: Vec<Version> = installed.iter().filter_map(|v| Version::parse(v)).collect();
+    versions.sort();
+
+    println!("  {:<14} {:<14} {:<14} MAJOR", "VERSION", "PATCH", "MINOR");
+
+    for version in versions {
+        let newest = |scope| {
+            newest_in(&version, scope, &releases)
+                .map(Version::to_string)
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let tag = version.to_string();
+        let marker = if active.as_ref() == Some(&tag) {
+            "*"
+        } else {
+            " "
+        };
+
+        println!(
+            "{} {:<14} {:<14} {:<14} {}",
+            marker,
+            tag,
+            newest(UpgradeScope::Patch),
+            newest(UpgradeScope::Minor),
+            newest(UpgradeScope::Major)
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a one-line notice when the release index has a newer release than the active version,
+/// and refresh the index in the background once it is older than the configured interval.
+pub fn notify(felloe: &Felloe) {
+    if let Err(e) = check(felloe) {
+        debug!("Unable to check for new releases: {}", e);
+    }
+}
+
+fn check(felloe: &Felloe) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    if !config.notify.enabled || !atty::is(Stream::Stderr) {
+        return Ok(());
+    }
+
+    let interval = config.notify.interval_hours * 60 * 60;
+    let now = now();
+    let mut index = ReleaseIndex::load(felloe);
+
+    if now.saturating_sub(index.notified_at) >= interval {
+        if let Some(notice) = notice(felloe, &index) {
+            eprintln!("{}", notice);
+            index.notified_at = now;
+            index.save(felloe)?;
+        }
+    }
+
+    if now.saturating_sub(index.checked_at) >= interval {
+        // Record the attempt first so a failing check is not retried on every command
+        index.checked_at = now;
+        index.save(felloe)?;
+
+        debug!("Refreshing the release index in the background");
+        Command::new(env::current_exe()?)
+            .args(["outdated", "--refresh-index"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+    }
+
+    Ok(())
+}
+
+fn notice(felloe: &Felloe, index: &ReleaseIndex) -> Option<String> {
+    let active = felloe.active().ok()??;
+    let current = Version::parse(&active)?;
+    let releases = index.versions();
+
+    let newest = newest_in(&current, UpgradeScope::Minor, &releases)
+        .or_else(|| newest_in(&current, UpgradeScope::Major, &releases))?;
+
+    let flag = if newest.major != current.major {
+        " --major"
+    } else if newest.minor != current.minor {
+        " --minor"
+    } else {
+        ""
+    };
+
+    Some(format!(
+        "helm {} is available (active: {}). Run `felloe upgrade{}` to install it",
+        newest, active, flag
+    ))
+}
@@ -0,0 +1,791 @@
+use crate::commands::{
+    after_activate, dir_size, download_versions, fetch_content_length, install, plural,
+};
+use crate::constants::{DEFAULT_DOWNLOAD_JOBS, RELEASE_INDEX_SIZE};
+use crate::felloe::Felloe;
+use crate::keymap::{key_name, Action, KeyMap};
+use crate::platform::Platform;
+use crate::release::{is_prerelease, Release, Version};
+use crate::theme::Theme;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    input::{input, AsyncReader, InputEvent, KeyEvent},
+    queue,
+    screen::{EnterAlternateScreen, LeaveAlternateScreen, RawScreen},
+    style::{Attribute, SetAttribute},
+    terminal::{self, Clear, ClearType},
+    Output,
+};
+use indicatif::HumanBytes;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{stdout, Stdout, Write},
+    thread,
+    time::Duration,
+};
+
+/// Lines above the version list: the title and the search line.
+const HEADER_HEIGHT: u16 = 2;
+/// Lines below the version list: the message line, the status bar and the key hints.
+const FOOTER_HEIGHT: u16 = 3;
+/// How often to check for key presses and terminal resizes while idle.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Key hints for the footer, dropped from the end when the terminal is too narrow.
+const KEY_HINTS: &[(&[Action], &str)] = &[
+    (&[Action::Up, Action::Down], "move"),
+    (&[Action::Select], "install/activate"),
+    (&[Action::Search], "search"),
+    (&[Action::Mark], "mark"),
+    (&[Action::Remove], "remove"),
+    (&[Action::Download], "download"),
+    (&[Action::Remote], "remote"),
+    (&[Action::Quit], "quit"),
+];
+
+/// A version listed in the picker, either downloaded or available upstream.
+#[derive(Debug)]
+struct Row {
+    version: String,
+    installed: bool,
+    prerelease: bool,
+    size: Option<u64>,
+}
+
+impl Row {
+    fn installed(version: String, size: u64) -> Row {
+        Row {
+            prerelease: is_prerelease(&version),
+            size: Some(size),
+            installed: true,
+            version,
+        }
+    }
+}
+
+/// A batch action waiting for the user to confirm it.
+#[derive(Debug)]
+enum Pending {
+    Remove(Vec<String>),
+    Download(Vec<String>),
+}
+
+/// The alternate screen in raw mode, restored when dropped so errors never leave the terminal
+/// in a broken state.
+struct Screen {
+    raw: Option<RawScreen>,
+    keys: Option<AsyncReader>,
+}
+
+impl Screen {
+    fn enter() -> Result<Screen, failure::Error> {
+        let mut stdout = stdout();
+        queue!(stdout, EnterAlternateScreen, Hide)?;
+        stdout.flush()?;
+
+        Ok(Screen {
+            raw: Some(RawScreen::into_raw_mode()?),
+            keys: Some(input().read_async()),
+        })
+    }
+
+    fn leave(&mut self) -> Result<(), failure::Error> {
+        self.keys = None;
+        self.raw = None;
+
+        let mut stdout = stdout();
+        queue!(stdout, Show, LeaveAlternateScreen)?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+
+    /// Return to the normal terminal while `f` runs so its output and progress stay visible,
+    /// then wait for a key press so they can be read before the picker comes back.
+    fn suspend<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, failure::Error> {
+        self.leave()?;
+        let result = f();
+
+        let mut stdout = stdout();
+        queue!(stdout, Output("\nPress any key to return to the picker"))?;
+        stdout.flush()?;
+        {
+            let _raw = RawScreen::into_raw_mode()?;
+            input()
+                .read_sync()
+                .find(|event| matches!(event, InputEvent::Keyboard(_)));
+        }
+
+        queue!(stdout, EnterAlternateScreen, Hide)?;
+        stdout.flush()?;
+        self.raw = Some(RawScreen::into_raw_mode()?);
+        self.keys = Some(input().read_async());
+
+        Ok(result)
+    }
+
+    /// Wait for the next key press. crossterm does not report terminal resizes, so the size is
+    /// polled meanwhile and `resized` called whenever it changes.
+    fn next_key(
+        &mut self,
+        mut resized: impl FnMut() -> Result<(), failure::Error>,
+    ) -> Result<KeyEvent, failure::Error> {
+        let mut size = terminal::size()?;
+
+        loop {
+            match self.keys.as_mut().and_then(Iterator::next) {
+                Some(InputEvent::Keyboard(key)) => return Ok(key),
+                Some(_) => continue,
+                None => {}
+            }
+
+            let current = terminal::size()?;
+            if current != size {
+                size = current;
+                resized()?;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        if self.raw.is_some() {
+            self.leave().ok();
+        }
+    }
+}
+
+/// State of the interactive version picker.
+struct Picker<'a> {
+    felloe: &'a Felloe,
+    rows: Vec<Row>,
+    /// Indices into `rows` that match the search query
+    visible: Vec<usize>,
+    /// Position of the selected row in `visible`
+    cursor: usize,
+    /// Position in `visible` of the first row on screen
+    scroll: usize,
+    /// Number of rows on screen, as of the last draw
+    page: usize,
+    query: String,
+    searching: bool,
+    remote: bool,
+    active: Option<String>,
+    /// Versions marked for a batch action
+    marked: HashSet<String>,
+    /// Disk usage of installed versions, so refreshing the list does not walk every directory
+    sizes: HashMap<String, u64>,
+    pending: Option<Pending>,
+    status: String,
+    keys: KeyMap,
+    theme: Theme,
+}
+
+impl<'a> Picker<'a> {
+    fn new(felloe: &'a Felloe, keys: KeyMap, theme: Theme) -> Picker<'a> {
+        let mut picker = Picker {
+            felloe,
+            rows: Vec::new(),
+            visible: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            page: 0,
+            query: String::new(),
+            searching: false,
+            remote: false,
+            active: None,
+            marked: HashSet::new(),
+            sizes: HashMap::new(),
+            pending: None,
+            status: String::new(),
+            keys,
+            theme,
+        };
+
+        picker.reload();
+        if let Some(active) = picker.active.clone() {
+            picker.select(&active);
+        }
+
+        if picker.rows.is_empty() {
+            picker.status =
+                "No versions are installed. Press r to show remote versions".to_string();
+        }
+
+        picker
+    }
+
+    /// Re-read installed versions, keeping remote versions that are still not installed.
+    fn reload(&mut self) {
+        self.active = self.felloe.active().unwrap_or_default();
+
+        let installed = self.felloe.installed_versions().unwrap_or_default();
+        self.rows
+            .retain(|row| !row.installed && !installed.contains(&row.version));
+        self.sizes.retain(|version, _| installed.contains(version));
+
+        let (felloe, sizes) = (self.felloe, &mut self.sizes);
+        self.rows.extend(installed.into_iter().map(|version| {
+            let size = *sizes
+                .entry(version.clone())
+                .or_insert_with(|| dir_size(&felloe.cache_path(&version)));
+            Row::installed(version, size)
+        }));
+        sort_rows(&mut self.rows);
+
+        self.refilter();
+    }
+
+    /// Show or hide versions that are only available upstream.
+    fn toggle_remote(&mut self) -> Result<(), failure::Error> {
+        if self.remote {
+            self.rows.retain(|row| row.installed);
+            self.remote = false;
+        } else {
+            let releases = self.felloe.fetch_releases(RELEASE_INDEX_SIZE, true)?;
+
+            for release in releases.0 {
+                if !self.rows.iter().any(|row| row.version == release.tag_name) {
+                    self.rows.push(Row {
+                        version: release.tag_name,
+                        installed: false,
+                        prerelease: release.prerelease,
+                        size: None,
+                    });
+                }
+            }
+
+            sort_rows(&mut self.rows);
+            self.remote = true;
+        }
+
+        let selected = self.selected().map(|row| row.version.clone());
+        self.refilter();
+        if let Some(selected) = selected {
+            self.select(&selected);
+        }
+
+        Ok(())
+    }
+
+    fn refilter(&mut self) {
+        let query = &self.query;
+        self.visible = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| fuzzy_match(query, &row.version))
+            .map(|(i, _)| i)
+            .collect();
+
+        self.cursor = self.cursor.min(self.visible.len().saturating_sub(1));
+    }
+
+    fn selected(&self) -> Option<&Row> {
+        self.visible.get(self.cursor).map(|&i| &self.rows[i])
+    }
+
+    fn select(&mut self, version: &str) {
+        if let Some(position) = self
+            .visible
+            .iter()
+            .position(|&i| self.rows[i].version == version)
+        {
+            self.cursor = position;
+        }
+    }
+
+    /// Mark or unmark the selected version and move to the next one.
+    fn toggle_mark(&mut self) {
+        let version = match self.selected() {
+            Some(row) => row.version.clone(),
+            None => return,
+        };
+
+        if !self.marked.remove(&version) {
+            self.marked.insert(version);
+        }
+        self.move_cursor(1);
+    }
+
+    /// The marked versions that are `installed` or not, newest first, or the selected version
+    /// when nothing is marked.
+    fn targets(&self, installed: bool) -> Vec<&Row> {
+        if self.marked.is_empty() {
+            return self
+                .selected()
+                .filter(|row| row.installed == installed)
+                .into_iter()
+                .collect();
+        }
+
+        self.rows
+            .iter()
+            .filter(|row| row.installed == installed && self.marked.contains(&row.version))
+            .collect()
+    }
+
+    /// Ask to remove the marked or selected installed versions, never the active one.
+    fn confirm_remove(&mut self) {
+        let (active, versions): (Vec<&Row>, Vec<&Row>) = self
+            .targets(true)
+            .into_iter()
+            .partition(|row| self.active.as_ref() == Some(&row.version));
+
+        if versions.is_empty() {
+            self.status = match active.first() {
+                Some(row) => format!("Cannot remove active version: {}", row.version),
+                None => "Nothing to remove. Mark installed versions with space".to_string(),
+            };
+            return;
+        }
+
+        let freed: u64 = versions.iter().filter_map(|row| row.size).sum();
+        let skipped = match active.first() {
+            Some(row) => format!(" (keeping active {})", row.version),
+            None => String::new(),
+        };
+
+        let status = format!(
+            "Remove {} and free {}{}? [y/N]",
+            describe(&versions),
+            HumanBytes(freed),
+            skipped
+        );
+        self.pending = Some(Pending::Remove(
+            versions.iter().map(|row| row.version.clone()).collect(),
+        ));
+        self.status = status;
+    }
+
+    /// Ask to download the marked or selected remote versions.
+    fn confirm_download(&mut self) {
+        let versions = self.targets(false);
+
+        if versions.is_empty() {
+            self.status = "Nothing to download. Mark remote versions with space".to_string();
+            return;
+        }
+
+        let platform = Platform::current();
+        let size = versions
+            .iter()
+            .map(|row| {
+                let archive = self
+                    .felloe
+                    .archive(&Release::from_tag(&row.version), &platform)?;
+                fetch_content_length(&archive.url)
+            })
+            .sum::<Result<u64, failure::Error>>()
+            .map(|size| HumanBytes(size).to_string())
+            .unwrap_or_else(|_| "an unknown size".to_string());
+
+        let status = format!("Download {}, {}? [y/N]", describe(&versions), size);
+        self.pending = Some(Pending::Download(
+            versions.iter().map(|row| row.version.clone()).collect(),
+        ));
+        self.status = status;
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let last = self.visible.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).max(0).min(last) as usize;
+    }
+
+    /// Keep the cursor within the `height` rows on screen.
+    fn scroll_to_cursor(&mut self, height: usize) {
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if height > 0 && self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+    }
+
+    fn draw(&mut self, w: &mut Stdout) -> Result<(), failure::Error> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        self.page = height.saturating_sub(HEADER_HEIGHT + FOOTER_HEIGHT) as usize;
+        self.scroll_to_cursor(self.page);
+
+        queue!(w, Clear(ClearType::All), MoveTo(0, 0))?;
+
+        if self.page == 0 {
+            queue!(w, Output(fit("Terminal too small", width)))?;
+            w.flush()?;
+            return Ok(());
+        }
+
+        let mut title = if self.remote {
+            "helm versions (installed and remote)".to_string()
+        } else {
+            "helm versions (installed)".to_string()
+        };
+        if !self.marked.is_empty() {
+            title.push_str(&format!(", {} marked", self.marked.len()));
+        }
+        queue!(
+            w,
+            SetAttribute(Attribute::Bold),
+            Output(fit(&title, width)),
+            SetAttribute(Attribute::Reset),
+            Output("\r\n")
+        )?;
+
+        if self.searching || !self.query.is_empty() {
+            let cursor = if self.searching { "_" } else { "" };
+            queue!(w, Output(fit(&format!("/{}{}", self.query, cursor), width)))?;
+        }
+        queue!(w, Output("\r\n"))?;
+
+        for (position, &i) in self
+            .visible
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(self.page)
+        {
+            let row = &self.rows[i];
+            let line = fit(&self.format_row(row, position == self.cursor), width);
+
+            if position == self.cursor {
+                self.theme.selected.apply(w)?;
+            } else if !row.installed {
+                self.theme.remote.apply(w)?;
+            }
+
+            queue!(
+                w,
+                Output(line),
+                SetAttribute(Attribute::Reset),
+                Output("\r\n")
+            )?;
+        }
+
+        queue!(
+            w,
+            MoveTo(0, height.saturating_sub(FOOTER_HEIGHT)),
+            Output(fit(&self.status, width)),
+            MoveTo(0, height.saturating_sub(2)),
+            SetAttribute(Attribute::Reverse),
+            Output(self.status_bar(width)),
+            SetAttribute(Attribute::Reset),
+            MoveTo(0, height.saturating_sub(1))
+        )?;
+        self.theme.hints.apply(w)?;
+        queue!(
+            w,
+            Output(self.key_hints(width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        w.flush()?;
+        Ok(())
+    }
+
+    /// The selected version on the left and the position in the list on the right, padded to
+    /// the full width.
+    fn status_bar(&self, width: usize) -> String {
+        let selection = match self.selected() {
+            Some(row) => {
+                let mut parts = vec![row.version.clone()];
+                if self.active.as_ref() == Some(&row.version) {
+                    parts.push("active".to_string());
+                }
+                parts.push(if row.installed { "installed" } else { "remote" }.to_string());
+                if let Some(size) = row.size {
+                    parts.push(HumanBytes(size).to_string());
+                }
+                let separator = if self.theme.ascii { ", " } else { " · " };
+                format!(" {}", parts.join(separator))
+            }
+            None => " no matching versions".to_string(),
+        };
+
+        let position = if self.visible.is_empty() {
+            String::new()
+        } else {
+            format!("{}/{} ", self.cursor + 1, self.visible.len())
+        };
+
+        let left = fit(&selection, width.saturating_sub(position.chars().count()));
+        let padding = width.saturating_sub(left.chars().count() + position.chars().count());
+        fit(
+            &format!("{}{}{}", left, " ".repeat(padding), position),
+            width,
+        )
+    }
+
+    /// As many key hints as fit in `width` characters, naming the first key of each action.
+    fn key_hints(&self, width: usize) -> String {
+        let mut hints = String::new();
+
+        for (actions, description) in KEY_HINTS {
+            let keys: Vec<String> = actions
+                .iter()
+                .filter_map(|action| self.keys.key(*action))
+                .map(|key| key_name(key, self.theme.ascii))
+                .collect();
+            if keys.is_empty() {
+                continue;
+            }
+
+            let hint = format!("{} {}", keys.join("/"), description);
+            let separator = if hints.is_empty() { "" } else { "  " };
+            if hints.chars().count() + separator.len() + hint.chars().count() > width {
+                break;
+            }
+            hints.push_str(separator);
+            hints.push_str(&hint);
+        }
+
+        hints
+    }
+
+    fn format_row(&self, row: &Row, selected: bool) -> String {
+        let (pointer, mark) = if self.theme.ascii {
+            (">", "x")
+        } else {
+            ("‣", "✓")
+        };
+        let pointer = if selected { pointer } else { " " };
+        let mark = if self.marked.contains(&row.version) {
+            mark
+        } else {
+            " "
+        };
+        let active = if self.active.as_ref() == Some(&row.version) {
+            "*"
+        } else {
+            " "
+        };
+        let state = if row.installed { "installed" } else { "remote" };
+        let prerelease = if row.prerelease { "pre" } else { "" };
+        let size = row
+            .size
+            .map(|size| HumanBytes(size).to_string())
+            .unwrap_or_default();
+
+        format!(
+            "{}{} {} {:<16} {:<10} {:<4} {:>10}",
+            pointer, mark, active, row.version, state, prerelease, size
+        )
+    }
+}
+
+/// Browse installed and remote versions, activating, installing and removing them until the
+/// user quits.
+pub fn select_version(felloe: &Felloe) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    let keys = KeyMap::load(&config.picker.keys)?;
+    let theme = Theme::load(&config.picker)?;
+
+    let mut picker = Picker::new(felloe, keys, theme);
+    let mut screen = Screen::enter()?;
+    let mut stdout = stdout();
+
+    loop {
+        picker.draw(&mut stdout)?;
+
+        let key = screen.next_key(|| picker.draw(&mut stdout))?;
+
+        if picker.searching {
+            match key {
+                KeyEvent::Esc => {
+                    picker.searching = false;
+                    picker.query.clear();
+                }
+                KeyEvent::Enter => picker.searching = false,
+                KeyEvent::Backspace => {
+                    picker.query.pop();
+                }
+                KeyEvent::Up => picker.move_cursor(-1),
+                KeyEvent::Down => picker.move_cursor(1),
+                KeyEvent::Ctrl('c') => break,
+                KeyEvent::Char(c) => picker.query.push(c),
+                _ => continue,
+            }
+
+            picker.refilter();
+            continue;
+        }
+
+        if let Some(pending) = picker.pending.take() {
+            picker.status = match key {
+                KeyEvent::Char('y') | KeyEvent::Char('Y') => match pending {
+                    Pending::Remove(versions) => remove_marked(&mut picker, versions),
+                    Pending::Download(versions) => {
+                        screen.suspend(|| download_marked(&mut picker, versions))?
+                    }
+                },
+                _ => "Cancelled".to_string(),
+            };
+            continue;
+        }
+
+        picker.status.clear();
+
+        // Ctrl-C and Esc keep their meaning whatever the configured bindings
+        match key {
+            KeyEvent::Ctrl('c') => break,
+            KeyEvent::Esc if picker.query.is_empty() => {
+                picker.marked.clear();
+                continue;
+            }
+            KeyEvent::Esc => {
+                picker.query.clear();
+                picker.refilter();
+                continue;
+            }
+            _ => {}
+        }
+
+        let action = match (picker.keys.action(key), key) {
+            (Some(action), _) => action,
+            // Typing anything that is not bound to an action starts a search, e.g. `3.2`
+            (None, KeyEvent::Char(c)) if !c.is_whitespace() && !c.is_control() => {
+                picker.searching = true;
+                picker.query.push(c);
+                picker.refilter();
+                continue;
+            }
+            (None, KeyEvent::Backspace) if !picker.query.is_empty() => {
+                picker.searching = true;
+                picker.query.pop();
+                picker.refilter();
+                continue;
+            }
+            _ => continue,
+        };
+
+        match action {
+            Action::Quit => break,
+            Action::Mark => picker.toggle_mark(),
+            Action::Search => picker.searching = true,
+            Action::Up => picker.move_cursor(-1),
+            Action::Down => picker.move_cursor(1),
+            Action::PageUp => picker.move_cursor(-(picker.page.max(1) as isize)),
+            Action::PageDown => picker.move_cursor(picker.page.max(1) as isize),
+            Action::Top => picker.cursor = 0,
+            Action::Bottom => picker.move_cursor(picker.visible.len() as isize),
+            Action::Remote => {
+                picker.status = "Fetching releases...".to_string();
+                picker.draw(&mut stdout)?;
+
+                picker.status = match picker.toggle_remote() {
+                    Ok(()) => String::new(),
+                    Err(e) => format!("Unable to fetch releases: {}", e),
+                };
+            }
+            Action::Select => {
+                let (version, installed) = match picker.selected() {
+                    Some(row) => (row.version.clone(), row.installed),
+                    None => continue,
+                };
+
+                let result = if installed {
+                    screen.suspend(|| {
+                        let activated = felloe.activate(&version)?;
+                        after_activate(felloe, activated.previous, &version);
+                        Ok(())
+                    })?
+                } else {
+                    screen.suspend(|| install(felloe, &version, &Platform::current()))?
+                };
+
+                picker.status = match result {
+                    Ok(()) => format!("Activated helm {}", version),
+                    Err(e) => format!("Unable to activate helm {}: {}", version, e),
+                };
+                picker.reload();
+                picker.select(&version);
+            }
+            Action::Remove => picker.confirm_remove(),
+            Action::Download => {
+                picker.status = "Checking download sizes...".to_string();
+                picker.draw(&mut stdout)?;
+                picker.confirm_download();
+            }
+        }
+    }
+
+    screen.leave()?;
+    Ok(())
+}
+
+fn remove_marked(picker: &mut Picker, versions: Vec<String>) -> String {
+    let freed: u64 = picker
+        .rows
+        .iter()
+        .filter(|row| versions.contains(&row.version))
+        .filter_map(|row| row.size)
+        .sum();
+
+    let status = match picker.felloe.remove(&versions, false) {
+        Ok(()) => format!(
+            "Removed {} and freed {}",
+            plural(versions.len(), "version"),
+            HumanBytes(freed)
+        ),
+        Err(e) => format!("Unable to remove {}: {}", versions.join(", "), e),
+    };
+
+    for version in &versions {
+        picker.marked.remove(version);
+    }
+    picker.reload();
+    status
+}
+
+fn download_marked(picker: &mut Picker, versions: Vec<String>) -> String {
+    let count = versions.len();
+    let result = download_versions(
+        picker.felloe,
+        versions.clone(),
+        &Platform::current(),
+        DEFAULT_DOWNLOAD_JOBS,
+    );
+
+    for version in &versions {
+        picker.marked.remove(version);
+    }
+    picker.reload();
+
+    match result {
+        Ok(()) => format!("Downloaded {}", plural(count, "version")),
+        Err(e) => format!("Unable to download: {}", e),
+    }
+}
+
+/// `helm v3.2.4` for a single row, `3 versions` otherwise.
+fn describe(rows: &[&Row]) -> String {
+    match rows {
+        [row] => format!("helm {}", row.version),
+        _ => plural(rows.len(), "version"),
+    }
+}
+
+/// Newest versions first, falling back to reverse name order for unparseable versions.
+fn sort_rows(rows: &mut [Row]) {
+    rows.sort_by(
+        |a, b| match (Version::parse(&a.version), Version::parse(&b.version)) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            _ => b.version.cmp(&a.version),
+        },
+    );
+}
+
+/// Whether every character of `query` appears in `text` in order, ignoring case.
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query
+        .chars()
+        .all(|q| chars.any(|c| c.eq_ignore_ascii_case(&q)))
+}
+
+/// Cut `line` to at most `width` characters so it never wraps.
+fn fit(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
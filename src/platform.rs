@@ -0,0 +1,89 @@
+use crate::constants::{ARCH, OS, SUPPORTED_ARCHS, SUPPORTED_OSES};
+use std::{fmt, str::FromStr};
+
+/// An operating system and architecture pair that helm publishes binaries for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+}
+
+impl Platform {
+    /// The platform felloe was compiled for.
+    pub fn current() -> Platform {
+        Platform {
+            os: OS.to_string(),
+            arch: ARCH.to_string(),
+        }
+    }
+
+    /// Build a platform from optional overrides, defaulting each part to the host.
+    pub fn new(os: Option<String>, arch: Option<String>) -> Result<Platform, failure::Error> {
+        let os = os.unwrap_or_else(|| OS.to_string());
+        let arch = arch.unwrap_or_else(|| ARCH.to_string());
+
+        if !SUPPORTED_OSES.contains(&os.as_str()) {
+            return Err(failure::err_msg(format!(
+                "Unsupported operating system {}. Expected one of: {}",
+                os,
+                SUPPORTED_OSES.join(", ")
+            )));
+        }
+
+        if !SUPPORTED_ARCHS.contains(&arch.as_str()) {
+            return Err(failure::err_msg(format!(
+                "Unsupported architecture {}. Expected one of: {}",
+                arch,
+                SUPPORTED_ARCHS.join(", ")
+            )));
+        }
+
+        Ok(Platform { os, arch })
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.os == OS && self.arch == ARCH
+    }
+
+    pub fn helm_bin_name(&self) -> &'static str {
+        if self.os == "windows" {
+            "helm.exe"
+        } else {
+            "helm"
+        }
+    }
+
+    pub fn tiller_bin_name(&self) -> &'static str {
+        if self.os == "windows" {
+            "tiller.exe"
+        } else {
+            "tiller"
+        }
+    }
+
+    /// Name of the release archive for `version` on this platform.
+    pub fn archive_name(&self, version: &str) -> String {
+        format!("helm-{}-{}.tar.gz", version, self)
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.os, self.arch)
+    }
+}
+
+impl FromStr for Platform {
+    type Err = failure::Error;
+
+    /// Parse a platform written as `<os>-<arch>`, the form it is displayed in.
+    fn from_str(s: &str) -> Result<Platform, failure::Error> {
+        match s.split_once('-') {
+            Some((os, arch)) => Platform::new(Some(os.to_string()), Some(arch.to_string())),
+            None => Err(failure::err_msg(format!(
+                "Invalid platform {}. Expected <os>-<arch>, e.g. linux-amd64",
+                s
+            ))),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::commands::run_helm;
+use crate::config::{Config, PluginSpec};
+use crate::felloe::Felloe;
+use crate::isolation::helm_dirs;
+use log::*;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A plugin installed for a helm version.
+#[derive(Debug)]
+pub struct InstalledPlugin {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginMetadata {
+    name: String,
+    #[serde(default)]
+    version: String,
+}
+
+/// Plugins installed for helm `version`, read from their `plugin.yaml` files.
+pub fn installed(
+    felloe: &Felloe,
+    version: &str,
+    config: &Config,
+) -> Result<Vec<InstalledPlugin>, failure::Error> {
+    let plugins_dir = helm_dirs(felloe, version, config).plugins_dir;
+    if !plugins_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut plugins = Vec::new();
+    for entry in plugins_dir.read_dir()? {
+        let path = entry?.path();
+        let metadata_path = path.join("plugin.yaml");
+
+        if !metadata_path.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&metadata_path)?;
+        let metadata: PluginMetadata = serde_yaml::from_str(&content)
+            .map_err(|e| failure::err_msg(format!("Invalid {}: {}", metadata_path.display(), e)))?;
+
+        plugins.push(InstalledPlugin {
+            name: metadata.name,
+            version: metadata.version,
+            path,
+        });
+    }
+
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(plugins)
+}
+
+pub fn list(felloe: &Felloe, version: &str) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+
+    for plugin in installed(felloe, version, &config)? {
+        println!("{:<20} {}", plugin.name, plugin.version);
+    }
+
+    Ok(())
+}
+
+fn install(felloe: &Felloe, version: &str, spec: &PluginSpec) -> Result<(), failure::Error> {
+    info!("Installing plugin {} for helm {}", spec.name, version);
+
+    match &spec.version {
+        None => run_helm(felloe, version, ["plugin", "install", spec.url.as_str()]),
+        Some(tag) => {
+            let result = run_helm(
+                felloe,
+                version,
+                ["plugin", "install", spec.url.as_str(), "--version", tag],
+            );
+
+            // plugin.yaml versions usually omit the `v` their git tags carry
+            match result {
+                Err(_) if !tag.starts_with('v') => run_helm(
+                    felloe,
+                    version,
+                    [
+                        "plugin",
+                        "install",
+                        spec.url.as_str(),
+                        "--version",
+                        &format!("v{}", tag),
+                    ],
+                ),
+                result => result,
+            }
+        }
+    }
+}
+
+/// Install `specs` for helm `version`, replacing plugins installed at another version.
+pub fn ensure(
+    felloe: &Felloe,
+    version: &str,
+    specs: &[PluginSpec],
+    config: &Config,
+) -> Result<(), failure::Error> {
+    let installed = installed(felloe, version, config)?;
+    let mut failed = Vec::new();
+
+    for spec in specs {
+        let current = installed.iter().find(|p| p.name == spec.name);
+
+        match (current, &spec.version) {
+            (Some(plugin), Some(pinned)) if !same_version(&plugin.version, pinned) => {
+                println!(
+                    "Replacing plugin {} {} with {} for helm {}",
+                    spec.name, plugin.version, pinned, version
+                );
+                if let Err(e) = run_helm(felloe, version, ["plugin", "remove", spec.name.as_str()])
+                {
+                    warn!("Unable to remove plugin {}: {}", spec.name, e);
+                    failed.push(spec.name.clone());
+                    continue;
+                }
+            }
+            (Some(_), _) => {
+                debug!("Plugin {} is already installed for {}", spec.name, version);
+                continue;
+            }
+            (None, _) => println!("Installing plugin {} for helm {}", spec.name, version),
+        }
+
+        if let Err(e) = install(felloe, version, spec) {
+            warn!("Unable to install plugin {}: {}", spec.name, e);
+            failed.push(spec.name.clone());
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(failure::err_msg(format!(
+            "Unable to reconcile plugins for helm {}: {}",
+            version,
+            failed.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Install the plugins listed in the config file for helm `version`.
+pub fn ensure_configured(felloe: &Felloe, version: &str) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+
+    ensure(felloe, version, &config.plugins, &config)
+}
+
+/// Install the plugins of helm `from` for helm `to`, using configured sources where available.
+pub fn sync(felloe: &Felloe, from: &str, to: &str) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    let mut specs = Vec::new();
+
+    for plugin in installed(felloe, from, &config)? {
+        let configured = config.plugins.iter().find(|p| p.name == plugin.name);
+
+        let url = match configured
+            .map(|p| p.url.clone())
+            .or_else(|| origin(&plugin.path))
+        {
+            Some(url) => url,
+            None => {
+                println!(
+                    "Unable to find where plugin {} was installed from. Add it to the plugins in {} to sync it",
+                    plugin.name,
+                    felloe.config_path().display()
+                );
+                continue;
+            }
+        };
+
+        let version = configured
+            .and_then(|p| p.version.clone())
+            .or_else(|| Some(plugin.version.clone()).filter(|v| !v.is_empty()));
+
+        specs.push(PluginSpec {
+            name: plugin.name,
+            url,
+            version,
+        });
+    }
+
+    ensure(felloe, to, &specs, &config)
+}
+
+/// The git remote a plugin was cloned from.
+fn origin(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["config", "--get", "remote.origin.url"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let url = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    Some(url).filter(|u| !u.is_empty())
+}
+
+fn same_version(installed: &str, pinned: &str) -> bool {
+    installed.trim_start_matches('v') == pinned.trim_start_matches('v')
+}
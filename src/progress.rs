@@ -1,19 +1,260 @@
-use indicatif::ProgressBar;
+use crate::constants::*;
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
 use std::{
     io::{self, Read},
-    sync::Arc,
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
 };
 
-pub struct DownloadProgress<R> {
+/// How download and install progress is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Animated bars and spinners for interactive terminals
+    Fancy,
+    /// Periodic log lines suitable for CI logs
+    Plain,
+    /// No progress output at all
+    None,
+}
+
+impl ProgressMode {
+    /// Fancy output when stdout is a terminal, plain output otherwise.
+    pub fn detect() -> ProgressMode {
+        if atty::is(atty::Stream::Stdout) {
+            ProgressMode::Fancy
+        } else {
+            ProgressMode::Plain
+        }
+    }
+}
+
+impl FromStr for ProgressMode {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fancy" => Ok(ProgressMode::Fancy),
+            "plain" => Ok(ProgressMode::Plain),
+            "none" => Ok(ProgressMode::None),
+            _ => Err(failure::err_msg(format!(
+                "Unknown progress mode {}. Expected one of: fancy, plain, none",
+                s
+            ))),
+        }
+    }
+}
+
+/// The stage a task is in once its download has completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Verify,
+    Unpack,
+}
+
+/// Creates progress tasks that render according to the configured mode.
+#[derive(Debug, Clone)]
+pub struct Reporter {
+    mode: ProgressMode,
+}
+
+impl Reporter {
+    pub fn new(mode: ProgressMode) -> Reporter {
+        Reporter { mode }
+    }
+
+    pub fn mode(&self) -> ProgressMode {
+        self.mode
+    }
+
+    /// A task drawn on its own.
+    pub fn task(&self) -> Task {
+        let bar = match self.mode {
+            ProgressMode::Fancy => ProgressBar::new_spinner(),
+            _ => ProgressBar::hidden(),
+        };
+
+        Task::new(self.mode, bar)
+    }
+
+    /// A group of tasks drawn together, one line per task.
+    pub fn group(&self) -> TaskGroup {
+        TaskGroup {
+            mode: self.mode,
+            multi: MultiProgress::new(),
+        }
+    }
+}
+
+pub struct TaskGroup {
+    mode: ProgressMode,
+    multi: MultiProgress,
+}
+
+impl TaskGroup {
+    pub fn task(&self) -> Task {
+        let bar = match self.mode {
+            ProgressMode::Fancy => self.multi.add(ProgressBar::new_spinner()),
+            _ => ProgressBar::hidden(),
+        };
+
+        Task::new(self.mode, bar)
+    }
+
+    /// Draw the group until every task has finished.
+    pub fn join(&self) -> Result<(), failure::Error> {
+        if self.mode == ProgressMode::Fancy {
+            self.multi.join()?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Transfer {
+    file_name: String,
+    length: u64,
+    position: u64,
+    reported: u64,
+    started: Instant,
+}
+
+/// Progress of a single unit of work, such as downloading and unpacking one release.
+pub struct Task {
+    mode: ProgressMode,
+    bar: ProgressBar,
+    transfer: Mutex<Option<Transfer>>,
+}
+
+impl Task {
+    /// A task that reports nothing.
+    pub fn hidden() -> Task {
+        Task::new(ProgressMode::None, ProgressBar::hidden())
+    }
+
+    fn new(mode: ProgressMode, bar: ProgressBar) -> Task {
+        Task {
+            mode,
+            bar,
+            transfer: Mutex::new(None),
+        }
+    }
+
+    pub fn message(&self, msg: &str) {
+        match self.mode {
+            ProgressMode::Fancy => self.bar.set_message(msg),
+            ProgressMode::Plain => eprintln!("{}", msg),
+            ProgressMode::None => {}
+        }
+    }
+
+    pub fn start_download(&self, file_name: &str, length: u64) {
+        *self.transfer.lock().unwrap() = Some(Transfer {
+            file_name: file_name.to_string(),
+            length,
+            position: 0,
+            reported: 0,
+            started: Instant::now(),
+        });
+
+        match self.mode {
+            ProgressMode::Fancy => {
+                self.bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template(BAR_STYLE_TEMPLATE)
+                        .progress_chars(BAR_PROGRESS_CHARS),
+                );
+                self.bar.set_length(length);
+                self.bar.set_position(0);
+                self.bar.set_message(file_name);
+            }
+            ProgressMode::Plain => eprintln!("Downloading {} ({})", file_name, HumanBytes(length)),
+            ProgressMode::None => {}
+        }
+    }
+
+    pub fn inc(&self, delta: u64) {
+        match self.mode {
+            ProgressMode::Fancy => self.bar.inc(delta),
+            ProgressMode::Plain => {
+                let mut transfer = self.transfer.lock().unwrap();
+                if let Some(t) = transfer.as_mut() {
+                    t.position += delta;
+
+                    if let Some(percent) = (t.position * 100).checked_div(t.length) {
+                        let step = percent / PLAIN_PROGRESS_STEP * PLAIN_PROGRESS_STEP;
+                        if step > t.reported && step < 100 {
+                            t.reported = step;
+                            eprintln!("{} {}%", t.file_name, step);
+                        }
+                    }
+                }
+            }
+            ProgressMode::None => {}
+        }
+    }
+
+    pub fn finish_download(&self) {
+        let transfer = self.transfer.lock().unwrap().take();
+
+        if let (ProgressMode::Plain, Some(t)) = (self.mode, transfer) {
+            eprintln!(
+                "Downloaded {} ({}) in {}",
+                t.file_name,
+                HumanBytes(t.position),
+                HumanDuration(t.started.elapsed())
+            );
+        }
+    }
+
+    pub fn stage(&self, stage: Stage, msg: &str) {
+        if self.mode == ProgressMode::Fancy {
+            let (chars, template, tick) = match stage {
+                Stage::Verify => (VERIFY_SPINNER_CHARS, VERIFY_SPINNER_TEMPLATE, 150),
+                Stage::Unpack => (UNPACK_SPINNER_CHARS, UNPACK_SPINNER_TEMPLATE, 120),
+            };
+
+            self.bar.set_style(
+                ProgressStyle::default_spinner()
+                    .tick_strings(chars)
+                    .template(template),
+            );
+            self.bar.enable_steady_tick(tick);
+        }
+
+        self.message(msg);
+    }
+
+    pub fn finish(&self, msg: &str) {
+        match self.mode {
+            ProgressMode::Fancy => {
+                self.bar.disable_steady_tick();
+                self.bar.finish_with_message(msg);
+            }
+            ProgressMode::Plain => eprintln!("{}", msg),
+            ProgressMode::None => {}
+        }
+    }
+
+    pub fn fail(&self, msg: &str) {
+        match self.mode {
+            ProgressMode::Fancy => {
+                self.bar.disable_steady_tick();
+                self.bar.abandon_with_message(msg);
+            }
+            ProgressMode::Plain => eprintln!("{}", msg),
+            ProgressMode::None => {}
+        }
+    }
+}
+
+pub struct DownloadProgress<'a, R> {
     pub stream: R,
-    pub pb: Arc<ProgressBar>,
+    pub task: &'a Task,
 }
 
-impl<R: Read> Read for DownloadProgress<R> {
+impl<'a, R: Read> Read for DownloadProgress<'a, R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.stream.read(buf).map(|n| {
-            self.pb.inc(n as u64);
-            n
-        })
+        self.stream.read(buf).inspect(|n| self.task.inc(*n as u64))
     }
 }
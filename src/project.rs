@@ -0,0 +1,111 @@
+use crate::constants::{LOCK_FILE_NAME, PROJECTS_FILE_NAME, VERSION_FILE_NAME};
+use crate::felloe::Felloe;
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Walk up from `dir` looking for the nearest `.helm-version` file.
+pub fn find_version_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(VERSION_FILE_NAME))
+        .find(|f| f.is_file())
+}
+
+/// Walk up from `dir` looking for the nearest `felloe.lock` file.
+pub fn find_lock_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(LOCK_FILE_NAME))
+        .find(|f| f.is_file())
+}
+
+/// Read the helm version pinned by a `.helm-version` file.
+pub fn read_version_file(path: &Path) -> Result<String, failure::Error> {
+    debug!("Reading helm version from {:?}", path);
+    let content = fs::read_to_string(path)?;
+
+    let version = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| failure::err_msg(format!("{} is empty", path.to_str().unwrap())))?;
+
+    Ok(normalize_version(version))
+}
+
+fn get_projects_path(felloe: &Felloe) -> PathBuf {
+    felloe.home().join(PROJECTS_FILE_NAME)
+}
+
+fn known_project_files(felloe: &Felloe) -> Vec<PathBuf> {
+    fs::read_to_string(get_projects_path(felloe))
+        .map(|content| content.lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+/// Remember `version_file` as a known project so `prune` keeps the version it pins. Only
+/// commands run explicitly for a project call this, never the shell hook.
+pub fn remember_project(felloe: &Felloe, version_file: &Path) {
+    if let Err(e) = record_project(felloe, version_file) {
+        debug!("Unable to record project {:?}: {}", version_file, e);
+    }
+}
+
+fn record_project(felloe: &Felloe, version_file: &Path) -> Result<(), failure::Error> {
+    let version_file = version_file.canonicalize()?;
+    let mut files = known_project_files(felloe);
+
+    if files.contains(&version_file) {
+        return Ok(());
+    }
+
+    files.push(version_file);
+    write_project_files(felloe, &files)
+}
+
+fn write_project_files(felloe: &Felloe, files: &[PathBuf]) -> Result<(), failure::Error> {
+    let content: Vec<_> = files.iter().map(|f| f.to_string_lossy()).collect();
+
+    fs::create_dir_all(felloe.home())?;
+    fs::write(get_projects_path(felloe), content.join("\n"))?;
+
+    Ok(())
+}
+
+/// Forget known projects whose `.helm-version` file no longer exists.
+pub fn forget_missing_projects(felloe: &Felloe) -> Result<(), failure::Error> {
+    let files = known_project_files(felloe);
+    let existing: Vec<PathBuf> = files.iter().filter(|f| f.is_file()).cloned().collect();
+
+    if existing.len() != files.len() {
+        write_project_files(felloe, &existing)?;
+    }
+
+    Ok(())
+}
+
+/// Versions pinned by the `.helm-version` files felloe has seen that still exist.
+pub fn known_project_versions(felloe: &Felloe) -> Result<Vec<(PathBuf, String)>, failure::Error> {
+    let mut versions = Vec::new();
+    for file in known_project_files(felloe)
+        .into_iter()
+        .filter(|f| f.is_file())
+    {
+        match read_version_file(&file) {
+            Ok(version) => versions.push((file, version)),
+            Err(e) => debug!("Skipping project {:?}: {}", file, e),
+        }
+    }
+
+    Ok(versions)
+}
+
+/// Helm release tags are prefixed with `v`; accept versions written without it.
+pub fn normalize_version(version: &str) -> String {
+    if version.starts_with('v') || version == "latest" {
+        version.to_string()
+    } else {
+        format!("v{}", version)
+    }
+}
@@ -0,0 +1,360 @@
+use crate::commands::dir_size;
+use crate::config::Config;
+use crate::confirm::Confirm;
+use crate::felloe::Felloe;
+use crate::metadata::{age, days_ago, VersionMetadata};
+use crate::project::{forget_missing_projects, known_project_versions, normalize_version};
+use crate::release::Version;
+use indicatif::HumanBytes;
+use log::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+/// Retention policies for `felloe prune`. Every version matched by a policy is kept.
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+    /// Keep the N newest versions
+    pub keep_latest: Option<usize>,
+    /// Keep the N newest patch versions of each minor version
+    pub keep_per_minor: Option<usize>,
+    /// Keep versions downloaded more recently than this
+    pub older_than: Option<Duration>,
+    /// Keep versions used more recently than this
+    pub unused_for: Option<Duration>,
+    /// Versions to keep
+    pub keep: Vec<String>,
+    pub confirm: Confirm,
+}
+
+/// Parse an age such as `90d`, `12h` or `2w`.
+pub fn parse_age(age: &str) -> Result<Duration, failure::Error> {
+    let invalid = || {
+        failure::err_msg(format!(
+            "Invalid age {}. Expected a number followed by s, m, h, d or w, e.g. 90d",
+            age
+        ))
+    };
+
+    let unit = age.chars().last().ok_or_else(invalid)?;
+    let count: u64 = age[..age.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    let seconds = count
+        .checked_mul(seconds)
+        .ok_or_else(|| failure::err_msg(format!("Age {} is too large", age)))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Why each installed version should be kept. Versions without a reason may be removed.
+fn kept_versions(
+    felloe: &Felloe,
+    installed: &[String],
+    opts: &PruneOptions,
+    config: &Config,
+) -> Result<HashMap<String, String>, failure::Error> {
+    let mut kept = HashMap::new();
+    let mut keep = |version: &str, reason: String| {
+        kept.entry(version.to_string()).or_insert(reason);
+    };
+
+    if let Ok(Some(active)) = felloe.active() {
+        keep(&active, "active".to_string());
+    }
+
+    for version in &opts.keep {
+        keep(&normalize_version(version), "kept with --keep".to_string());
+    }
+
+    for version in &config.prune.keep {
+        keep(
+            &normalize_version(version),
+            format!("pinned in {}", felloe.config_path().display()),
+        );
+    }
+
+    for (file, version) in known_project_versions(felloe)? {
+        keep(&version, format!("used by {}", file.display()));
+    }
+
+    let mut versions: Vec<(Version, &String)> = installed
+        .iter()
+        .filter_map(|v| Version::parse(v).map(|parsed| (parsed, v)))
+        .collect();
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+
+    if let Some(count) = opts.keep_latest {
+        for (_, version) in versions.iter().take(count) {
+            keep(version, newest_reason(count, ""));
+        }
+    }
+
+    if let Some(count) = opts.keep_per_minor {
+        let mut minors: BTreeMap<(u64, u64), Vec<&String>> = BTreeMap::new();
+        for (parsed, version) in &versions {
+            minors
+                .entry((parsed.major, parsed.minor))
+                .or_default()
+                .push(version);
+        }
+
+        for ((major, minor), versions) in minors {
+            for version in versions.into_iter().take(count) {
+                keep(
+                    version,
+                    newest_reason(count, &format!("v{}.{} ", major, minor)),
+                );
+            }
+        }
+    }
+
+    for version in installed {
+        let dir = felloe.cache_path(version);
+        let metadata = VersionMetadata::load_from(&dir);
+
+        if let Some(older_than) = opts.older_than {
+            let downloaded = metadata.downloaded_at(&dir);
+
+            if age(downloaded).is_some_and(|age| age < older_than) {
+                keep(version, format!("downloaded {}", days_ago(downloaded)));
+            }
+        }
+
+        if let Some(unused_for) = opts.unused_for {
+            let used = metadata.last_used(&dir);
+
+            if age(used).is_some_and(|age| age < unused_for) {
+                keep(version, format!("used {}", days_ago(used)));
+            }
+        }
+    }
+
+    Ok(kept)
+}
+
+fn newest_reason(count: usize, line: &str) -> String {
+    if count == 1 {
+        format!("the newest {}version", line)
+    } else {
+        format!("one of the {} newest {}versions", count, line)
+    }
+}
+
+/// Downloaded versions that no retention policy keeps.
+pub fn removable(felloe: &Felloe, opts: &PruneOptions) -> Result<Vec<String>, failure::Error> {
+    let config = felloe.config()?;
+    let mut installed = felloe.installed_versions()?;
+    installed.sort();
+
+    let kept = kept_versions(felloe, &installed, opts, &config)?;
+
+    let mut removable = Vec::new();
+    for version in installed {
+        match kept.get(&version) {
+            Some(reason) => info!("Keeping helm {}: {}", version, reason),
+            None => removable.push(version),
+        }
+    }
+
+    Ok(removable)
+}
+
+/// Remove downloaded versions that no retention policy keeps.
+pub fn prune(felloe: &Felloe, opts: &PruneOptions) -> Result<(), failure::Error> {
+    let removable = removable(felloe, opts)?;
+
+    if removable.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+
+    if !opts.confirm.removal(felloe, &removable)? {
+        return Ok(());
+    }
+
+    if let Err(e) = forget_missing_projects(felloe) {
+        debug!("Unable to update known projects: {}", e);
+    }
+
+    let sizes: Vec<u64> = removable
+        .iter()
+        .map(|v| dir_size(&felloe.cache_path(v)))
+        .collect();
+    let total = sizes.iter().sum::<u64>();
+
+    felloe.remove(&removable, false)?;
+
+    for (version, size) in removable.iter().zip(&sizes) {
+        println!("Removed helm {} ({})", version, HumanBytes(*size));
+    }
+    println!("Reclaimed {}", HumanBytes(total));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::now;
+    use crate::project::remember_project;
+    use std::{env, fs, process};
+
+    const INSTALLED: &[&str] = &[
+        "v2.16.12",
+        "v3.1.0",
+        "v3.1.3",
+        "v3.2.0",
+        "v3.2.4",
+        "v3.3.0-rc.1",
+    ];
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    /// A felloe with an empty home and bin directory of its own.
+    fn felloe(name: &str) -> Felloe {
+        let home = env::temp_dir().join(format!("felloe-prune-{}-{}", name, process::id()));
+        fs::create_dir_all(home.join("bin")).unwrap();
+
+        Felloe::from_env()
+            .unwrap()
+            .with_home(home.clone())
+            .with_bin_dir(home.join("bin"))
+            .with_config_path(home.join("config.toml"))
+    }
+
+    fn kept(felloe: &Felloe, opts: &PruneOptions, config: &Config) -> Vec<(String, String)> {
+        let installed: Vec<String> = INSTALLED.iter().map(|v| v.to_string()).collect();
+        let mut kept: Vec<_> = kept_versions(felloe, &installed, opts, config)
+            .unwrap()
+            .into_iter()
+            .collect();
+        kept.sort();
+
+        fs::remove_dir_all(felloe.home()).unwrap();
+        kept
+    }
+
+    fn versions(kept: &[(String, String)]) -> Vec<&str> {
+        kept.iter().map(|(version, _)| version.as_str()).collect()
+    }
+
+    #[test]
+    fn ages_take_a_unit() {
+        assert_eq!(parse_age("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 60 * 60));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(30 * DAY));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * DAY));
+    }
+
+    #[test]
+    fn malformed_ages_are_rejected() {
+        for age in &["", "d", "30", "30y", "-1d", "1.5d", "d30", "３d"] {
+            assert!(parse_age(age).is_err(), "{:?}", age);
+        }
+    }
+
+    #[test]
+    fn ages_that_overflow_are_rejected() {
+        let error = parse_age("30000000000000000w").unwrap_err();
+        assert!(error.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn nothing_is_kept_without_a_policy() {
+        let felloe = felloe("none");
+        let kept = kept(&felloe, &PruneOptions::default(), &Config::default());
+
+        assert!(kept.is_empty(), "{:?}", kept);
+    }
+
+    #[test]
+    fn keep_latest_keeps_the_newest_versions() {
+        let felloe = felloe("latest");
+        let opts = PruneOptions {
+            keep_latest: Some(2),
+            ..PruneOptions::default()
+        };
+
+        let kept = kept(&felloe, &opts, &Config::default());
+        assert_eq!(versions(&kept), ["v3.2.4", "v3.3.0-rc.1"]);
+    }
+
+    #[test]
+    fn keep_per_minor_keeps_the_newest_patch_of_each_minor() {
+        let felloe = felloe("minor");
+        let opts = PruneOptions {
+            keep_per_minor: Some(1),
+            ..PruneOptions::default()
+        };
+
+        let kept = kept(&felloe, &opts, &Config::default());
+        assert_eq!(
+            versions(&kept),
+            ["v2.16.12", "v3.1.3", "v3.2.4", "v3.3.0-rc.1"]
+        );
+    }
+
+    #[test]
+    fn keep_and_configured_pins_accept_versions_without_v() {
+        let felloe = felloe("keep");
+        let opts = PruneOptions {
+            keep: vec!["3.1.0".to_string()],
+            ..PruneOptions::default()
+        };
+        let mut config = Config::default();
+        config.prune.keep = vec!["3.2.0".to_string()];
+
+        let kept = kept(&felloe, &opts, &config);
+        assert_eq!(versions(&kept), ["v3.1.0", "v3.2.0"]);
+        assert_eq!(kept[0].1, "kept with --keep");
+        assert!(kept[1].1.starts_with("pinned in "));
+    }
+
+    #[test]
+    fn versions_of_known_projects_are_kept() {
+        let felloe = felloe("projects");
+        let project = felloe.home().join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join(".helm-version"), "3.1.3\n").unwrap();
+        remember_project(&felloe, &project.join(".helm-version"));
+
+        let kept = kept(&felloe, &PruneOptions::default(), &Config::default());
+        assert_eq!(versions(&kept), ["v3.1.3"]);
+        assert!(kept[0].1.starts_with("used by "));
+    }
+
+    #[test]
+    fn older_than_keeps_recent_downloads() {
+        let felloe = felloe("older");
+        for (version, days) in &[("v3.1.0", 100), ("v3.2.4", 1)] {
+            let dir = felloe.cache_path(version);
+            fs::create_dir_all(&dir).unwrap();
+
+            let metadata = VersionMetadata {
+                downloaded_at: Some(now() - days * DAY),
+                ..VersionMetadata::default()
+            };
+            metadata.save(&dir).unwrap();
+        }
+
+        let opts = PruneOptions {
+            older_than: Some(parse_age("30d").unwrap()),
+            ..PruneOptions::default()
+        };
+
+        let kept = kept(&felloe, &opts, &Config::default());
+        assert_eq!(versions(&kept), ["v3.2.4"]);
+    }
+}
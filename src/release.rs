@@ -1,10 +1,227 @@
 use serde::Deserialize;
+use std::{cmp::Ordering, fmt};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Release {
     pub tag_name: String,
     pub prerelease: bool,
+    /// Files attached to a GitHub release, or `None` when the release was not read from GitHub
+    #[serde(default)]
+    pub assets: Option<Vec<ReleaseAsset>>,
+}
+
+/// A file attached to a GitHub release.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// A release tagged `tag`, which is a prerelease when its suffix says so, e.g. `-rc.1`.
+    pub fn from_tag(tag: &str) -> Release {
+        Release {
+            tag_name: tag.to_string(),
+            prerelease: is_prerelease(tag),
+            assets: None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Releases(pub Vec<Release>);
+
+/// Alpha, beta and release candidate tags. Other suffixes, such as those of patched builds like
+/// `v3.2.4-acme.1`, are regular releases.
+pub fn is_prerelease(tag: &str) -> bool {
+    match Version::parse(tag).and_then(|v| v.pre) {
+        Some(pre) => ["alpha", "beta", "rc", "pre", "dev"]
+            .iter()
+            .any(|prefix| pre.starts_with(prefix)),
+        None => false,
+    }
+}
+
+/// Order releases by version, comparing tags that are not versions as strings.
+pub fn by_version(a: &Release, b: &Release) -> Ordering {
+    match (Version::parse(&a.tag_name), Version::parse(&b.tag_name)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.tag_name.cmp(&b.tag_name),
+    }
+}
+
+/// Helm 2 releases ship with tiller.
+pub fn is_helm2(version: &str) -> bool {
+    version.starts_with("v2.")
+}
+
+/// A semantic version parsed from a release tag such as `v3.2.4` or `v3.0.0-rc.1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Option<String>,
+}
+
+impl Version {
+    pub fn parse(tag: &str) -> Option<Version> {
+        let version = tag.trim_start_matches('v');
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (version, None),
+        };
+
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+            pre,
+        };
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(version)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+/// Compare prerelease suffixes as semver does: identifier by identifier, numbers numerically and
+/// below words, with fewer identifiers first when all shared ones are equal.
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(tag: &str) -> Version {
+        Version::parse(tag).unwrap()
+    }
+
+    fn assert_ascending(tags: &[&str]) {
+        for pair in tags.windows(2) {
+            assert!(
+                version(pair[0]) < version(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn releases_follow_their_prereleases() {
+        assert_ascending(&["v3.0.0-rc.1", "v3.0.0-rc.2", "v3.0.0-rc.10", "v3.0.0"]);
+    }
+
+    #[test]
+    fn prereleases_of_a_major_follow_older_releases() {
+        assert_ascending(&["v2.16.12", "v3.0.0-alpha.1", "v3.0.0-beta.1", "v3.0.0"]);
+    }
+
+    #[test]
+    fn core_versions_compare_numerically() {
+        assert_ascending(&["v2.9.1", "v2.10.0", "v2.16.9", "v2.16.12", "v10.0.0"]);
+    }
+
+    #[test]
+    fn prerelease_identifiers_follow_semver_precedence() {
+        assert_ascending(&[
+            "v1.0.0-alpha",
+            "v1.0.0-alpha.1",
+            "v1.0.0-alpha.beta",
+            "v1.0.0-beta",
+            "v1.0.0-beta.2",
+            "v1.0.0-beta.11",
+            "v1.0.0-rc.1",
+            "v1.0.0",
+        ]);
+    }
+
+    #[test]
+    fn the_v_prefix_is_optional() {
+        assert_eq!(version("3.2.4"), version("v3.2.4"));
+    }
+
+    #[test]
+    fn malformed_tags_are_not_versions() {
+        for tag in &["latest", "v3.2", "v3.2.4.1", "v3.x.0", "v-1.0.0", ""] {
+            assert_eq!(Version::parse(tag), None, "{:?}", tag);
+        }
+    }
+
+    #[test]
+    fn tags_that_are_not_versions_sort_as_strings() {
+        let mut releases = [
+            Release::from_tag("nightly"),
+            Release::from_tag("v3.0.0"),
+            Release::from_tag("canary"),
+        ];
+        releases.sort_by(by_version);
+
+        let tags: Vec<_> = releases.iter().map(|r| r.tag_name.as_str()).collect();
+        assert_eq!(tags, ["canary", "nightly", "v3.0.0"]);
+    }
+
+    #[test]
+    fn only_alpha_beta_and_release_candidates_are_prereleases() {
+        assert!(is_prerelease("v3.0.0-rc.1"));
+        assert!(is_prerelease("v3.0.0-beta.2"));
+        assert!(!is_prerelease("v3.2.4-acme.1"));
+        assert!(!is_prerelease("v3.2.4"));
+    }
+}
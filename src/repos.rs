@@ -0,0 +1,173 @@
+use crate::commands::{run_helm, run_helm_with_input};
+use crate::config::Config;
+use crate::felloe::Felloe;
+use crate::isolation::helm_dirs;
+use crate::release::is_helm2;
+use log::*;
+use serde::Deserialize;
+use std::fs;
+
+/// A chart repository entry from helm's `repositories.yaml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Repository {
+    pub name: String,
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    #[serde(rename = "caFile")]
+    pub ca_file: String,
+    #[serde(rename = "certFile")]
+    pub cert_file: String,
+    #[serde(rename = "keyFile")]
+    pub key_file: String,
+    pub insecure_skip_tls_verify: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RepositoryFile {
+    repositories: Vec<Repository>,
+}
+
+/// The chart repository helm 2 serves from `helm serve`, which is never reachable elsewhere.
+static HELM2_LOCAL_REPO_URL: &str = "http://127.0.0.1:8879/charts";
+
+/// Repositories configured for helm `version`.
+pub fn repositories(
+    felloe: &Felloe,
+    version: &str,
+    config: &Config,
+) -> Result<Vec<Repository>, failure::Error> {
+    let path = helm_dirs(felloe, version, config).repositories_file;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    debug!("Reading repositories from {:?}", path);
+    let content = fs::read_to_string(&path)?;
+    let file: RepositoryFile = serde_yaml::from_str(&content)
+        .map_err(|e| failure::err_msg(format!("Invalid {}: {}", path.display(), e)))?;
+
+    Ok(file.repositories)
+}
+
+pub fn list(felloe: &Felloe, version: &str) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+
+    for repo in repositories(felloe, version, &config)? {
+        println!("{:<20} {}", repo.name, repo.url);
+    }
+
+    Ok(())
+}
+
+/// Add the repositories of helm `from` to helm `to`, optionally updating them afterwards.
+pub fn sync(felloe: &Felloe, from: &str, to: &str, update: bool) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    let existing = repositories(felloe, to, &config)?;
+    let mut failed = Vec::new();
+    let mut added = 0;
+
+    for repo in repositories(felloe, from, &config)? {
+        if repo.url.trim_end_matches('/') == HELM2_LOCAL_REPO_URL {
+            debug!("Skipping helm 2 local repository {}", repo.name);
+            continue;
+        }
+
+        if existing
+            .iter()
+            .any(|r| r.name == repo.name && r.url == repo.url)
+        {
+            debug!("Repository {} already exists for {}", repo.name, to);
+            continue;
+        }
+
+        // helm 3 refuses to change the URL of an existing repository without --force-update
+        let replace = existing.iter().any(|r| r.name == repo.name);
+
+        println!("Adding repository {} to helm {}", repo.name, to);
+        match add(felloe, &repo, to, replace) {
+            Ok(()) => added += 1,
+            Err(e) => {
+                warn!("Unable to add repository {}: {}", repo.name, e);
+                failed.push(repo.name);
+            }
+        }
+    }
+
+    if update && (added > 0 || !existing.is_empty()) {
+        run_helm(felloe, to, ["repo", "update"])?;
+    }
+
+    if !failed.is_empty() {
+        return Err(failure::err_msg(format!(
+            "Unable to add repositories to helm {}: {}",
+            to,
+            failed.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Add `repo` to helm `version`, passing its password on stdin so it never shows up in the
+/// process list.
+fn add(
+    felloe: &Felloe,
+    repo: &Repository,
+    version: &str,
+    replace: bool,
+) -> Result<(), failure::Error> {
+    let args = add_args(repo, version, replace);
+
+    if repo.password.is_empty() {
+        return run_helm(felloe, version, args);
+    }
+
+    if is_helm2(version) {
+        return Err(failure::err_msg(format!(
+            "helm {} only takes repository passwords on the command line. Add {} by hand",
+            version, repo.name
+        )));
+    }
+
+    run_helm_with_input(felloe, version, args, &repo.password)
+}
+
+fn add_args(repo: &Repository, version: &str, replace: bool) -> Vec<String> {
+    let mut args = vec![
+        "repo".to_string(),
+        "add".to_string(),
+        repo.name.clone(),
+        repo.url.clone(),
+    ];
+
+    let flags = [
+        ("--username", &repo.username),
+        ("--ca-file", &repo.ca_file),
+        ("--cert-file", &repo.cert_file),
+        ("--key-file", &repo.key_file),
+    ];
+
+    for (flag, value) in flags.iter() {
+        if !value.is_empty() {
+            args.push(flag.to_string());
+            args.push(value.to_string());
+        }
+    }
+
+    if !repo.password.is_empty() {
+        args.push("--password-stdin".to_string());
+    }
+
+    if repo.insecure_skip_tls_verify && !is_helm2(version) {
+        args.push("--insecure-skip-tls-verify".to_string());
+    }
+
+    if replace && !is_helm2(version) {
+        args.push("--force-update".to_string());
+    }
+
+    args
+}
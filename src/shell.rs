@@ -0,0 +1,111 @@
+use std::env;
+use structopt::clap::Shell;
+
+/// Guess the user's shell from the environment, falling back to the platform default.
+pub fn detect() -> Shell {
+    let name = env::var("SHELL").unwrap_or_default();
+    let name = name.rsplit(['/', '\\']).next().unwrap_or("");
+
+    match name {
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        "elvish" => Shell::Elvish,
+        "pwsh" | "powershell" => Shell::PowerShell,
+        "bash" => Shell::Bash,
+        _ if cfg!(target_os = "windows") => Shell::PowerShell,
+        _ => Shell::Bash,
+    }
+}
+
+/// Render a statement that exports `name` with `value` in the given shell.
+pub fn export(shell: &Shell, name: &str, value: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("export {}={};", name, posix_quote(value)),
+        Shell::Fish if name == "PATH" => {
+            let paths: Vec<String> = env::split_paths(value)
+                .map(|p| fish_quote(&p.to_string_lossy()))
+                .collect();
+            format!("set -gx PATH {};", paths.join(" "))
+        }
+        Shell::Fish => format!("set -gx {} {};", name, fish_quote(value)),
+        Shell::PowerShell => format!("$Env:{} = {};", name, single_quote(value)),
+        Shell::Elvish => format!("set-env {} {}", name, single_quote(value)),
+    }
+}
+
+/// Render a statement that removes `name` from the environment of the given shell.
+pub fn unset(shell: &Shell, name: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("unset {};", name),
+        Shell::Fish => format!("set -e {};", name),
+        Shell::PowerShell => format!("Remove-Item Env:{} -ErrorAction SilentlyContinue;", name),
+        Shell::Elvish => format!("unset-env {}", name),
+    }
+}
+
+/// The hook that applies `.helm-version` files whenever the working directory changes.
+pub fn hook(shell: &Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH_HOOK,
+        Shell::Zsh => ZSH_HOOK,
+        Shell::Fish => FISH_HOOK,
+        Shell::PowerShell => POWERSHELL_HOOK,
+        Shell::Elvish => ELVISH_HOOK,
+    }
+}
+
+fn posix_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+static BASH_HOOK: &str = r#"_felloe_hook() {
+  local previous_exit_status=$?
+  if [[ "${_FELLOE_LAST_PWD:-}" != "$PWD" ]]; then
+    _FELLOE_LAST_PWD="$PWD"
+    eval "$(felloe shell --auto --shell bash)"
+  fi
+  return $previous_exit_status
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";_felloe_hook;"* ]]; then
+  PROMPT_COMMAND="_felloe_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+"#;
+
+static ZSH_HOOK: &str = r#"_felloe_hook() {
+  eval "$(felloe shell --auto --shell zsh)"
+}
+typeset -ag chpwd_functions
+if (( ! ${chpwd_functions[(I)_felloe_hook]} )); then
+  chpwd_functions=(_felloe_hook $chpwd_functions)
+fi
+_felloe_hook
+"#;
+
+static FISH_HOOK: &str = r#"function _felloe_hook --on-variable PWD
+    felloe shell --auto --shell fish | source
+end
+_felloe_hook
+"#;
+
+static POWERSHELL_HOOK: &str = r#"$global:_FelloeLastDir = $null
+$global:_FelloeOriginalPrompt = $function:prompt
+function global:prompt {
+    if ($PWD.Path -ne $global:_FelloeLastDir) {
+        $global:_FelloeLastDir = $PWD.Path
+        felloe shell --auto --shell powershell | Out-String | Invoke-Expression
+    }
+    & $global:_FelloeOriginalPrompt
+}
+"#;
+
+static ELVISH_HOOK: &str = r#"set after-chdir = [$@after-chdir {|_| eval (felloe shell --auto --shell elvish | slurp) }]
+eval (felloe shell --auto --shell elvish | slurp)
+"#;
@@ -0,0 +1,712 @@
+use crate::commands::{download_with, read_download};
+use crate::config::{ReleaseSourceKind, ReleasesConfig};
+use crate::platform::Platform;
+use crate::progress::Task;
+use crate::project::normalize_version;
+use crate::release::{by_version, Release, Version};
+use log::*;
+use regex::Regex;
+use reqwest::{
+    header::{ACCEPT, WWW_AUTHENTICATE},
+    Client, RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+};
+
+/// A release archive and the sha256 digest it must match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseArchive {
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Where helm releases are discovered, and where their archives are downloaded from when the
+/// source publishes them itself.
+pub trait ReleaseSource: fmt::Debug + fmt::Display + Send + Sync {
+    /// Up to `count` of the newest releases, prereleases included.
+    fn releases(&self, count: usize) -> Result<Vec<Release>, failure::Error>;
+
+    /// The release tagged `version`, or the newest release that is not a prerelease for
+    /// `latest`.
+    fn release(&self, version: &str) -> Result<Release, failure::Error> {
+        let releases = self.releases(usize::MAX)?;
+
+        let release = if version == "latest" {
+            releases
+                .into_iter()
+                .filter(|release| !release.prerelease)
+                .max_by(by_version)
+        } else {
+            releases
+                .into_iter()
+                .find(|release| release.tag_name == version)
+        };
+
+        release.ok_or_else(|| {
+            failure::err_msg(format!("helm {} is not available from {}", version, self))
+        })
+    }
+
+    /// The archive of `release` for `platform`, or `None` when the source does not publish
+    /// archives and they are downloaded from the mirror.
+    fn archive(
+        &self,
+        _release: &Release,
+        _platform: &Platform,
+    ) -> Result<Option<ReleaseArchive>, failure::Error> {
+        Ok(None)
+    }
+
+    /// Download the archive at `url`, named `file_name`, reporting progress on `task`.
+    fn download(
+        &self,
+        client: &Client,
+        url: &str,
+        _file_name: &str,
+        task: &Task,
+    ) -> Result<Vec<u8>, failure::Error> {
+        download_with(client, url, task)
+    }
+}
+
+/// The release source selected by the `[releases]` section of the config file.
+pub fn from_config(
+    config: &ReleasesConfig,
+    client: &Client,
+    mirror: &str,
+) -> Result<Box<dyn ReleaseSource>, failure::Error> {
+    let required = |value: &Option<String>, key: &str| {
+        value.clone().ok_or_else(|| {
+            failure::err_msg(format!(
+                "releases.{} is required for the {} release source",
+                key, config.source
+            ))
+        })
+    };
+
+    Ok(match config.source {
+        ReleaseSourceKind::Github => Box::new(
+            GitHub::new(
+                config.repository.as_deref().unwrap_or(GitHub::HELM),
+                client.clone(),
+            )
+            .with_mirror(config.mirror.as_deref()),
+        ),
+        ReleaseSourceKind::Index => Box::new(
+            IndexFile::new(&required(&config.url, "url")?, client.clone())
+                .with_mirror(config.mirror.as_deref()),
+        ),
+        ReleaseSourceKind::Directory => Box::new(Directory::new(
+            config.url.as_deref().unwrap_or(mirror),
+            client.clone(),
+        )),
+        ReleaseSourceKind::Oci => Box::new(Oci::new(
+            &required(&config.registry, "registry")?,
+            &required(&config.repository, "repository")?,
+            client.clone(),
+        )),
+    })
+}
+
+/// Releases of a GitHub repository. Archives attached to a release are downloaded from GitHub,
+/// along with the `.sha256` asset next to them.
+#[derive(Debug, Clone)]
+pub struct GitHub {
+    repository: String,
+    mirror: Option<String>,
+    client: Client,
+}
+
+impl GitHub {
+    pub const HELM: &'static str = "helm/helm";
+
+    /// Releases of `repository`, e.g. `helm/helm`.
+    pub fn new(repository: &str, client: Client) -> GitHub {
+        GitHub {
+            repository: repository.to_string(),
+            mirror: None,
+            client,
+        }
+    }
+
+    /// Download archives that are not attached to a release from `mirror` instead of the
+    /// Felloe mirror.
+    pub fn with_mirror(mut self, mirror: Option<&str>) -> GitHub {
+        self.mirror = mirror.map(String::from);
+        self
+    }
+
+    fn api(&self) -> String {
+        format!("https://api.github.com/repos/{}/releases", self.repository)
+    }
+}
+
+impl fmt::Display for GitHub {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GitHub repository {}", self.repository)
+    }
+}
+
+impl ReleaseSource for GitHub {
+    fn releases(&self, count: usize) -> Result<Vec<Release>, failure::Error> {
+        // GitHub returns at most 100 releases per page
+        let url = format!("{}?per_page={}", self.api(), count.min(100));
+
+        Ok(self.client.get(&url).send()?.error_for_status()?.json()?)
+    }
+
+    fn release(&self, version: &str) -> Result<Release, failure::Error> {
+        let url = if version == "latest" {
+            format!("{}/{}", self.api(), version)
+        } else {
+            format!("{}/tags/{}", self.api(), version)
+        };
+
+        let response = self.client.get(&url).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(failure::err_msg(format!(
+                "helm {} is not available from {}",
+                version, self
+            )));
+        }
+
+        Ok(response.error_for_status()?.json()?)
+    }
+
+    fn archive(
+        &self,
+        release: &Release,
+        platform: &Platform,
+    ) -> Result<Option<ReleaseArchive>, failure::Error> {
+        let version = &release.tag_name;
+
+        // helm's own releases only carry signatures, the archives are on the mirror
+        if self.repository == GitHub::HELM {
+            return mirror_archive(&self.client, self.mirror.as_deref(), version, platform);
+        }
+
+        // Releases looked up on GitHub already list their assets
+        let assets = match &release.assets {
+            Some(assets) => assets.clone(),
+            None => self.release(version)?.assets.unwrap_or_default(),
+        };
+
+        let name = platform.archive_name(version);
+        let asset = |name: &str| assets.iter().find(|asset| asset.name == name);
+
+        let archive = match asset(&name) {
+            Some(archive) => archive,
+            None => return mirror_archive(&self.client, self.mirror.as_deref(), version, platform),
+        };
+
+        let digest = asset(&format!("{}.sha256", name))
+            .or_else(|| asset(&format!("{}.sha256sum", name)))
+            .ok_or_else(|| {
+                failure::err_msg(format!(
+                    "helm {} on {} has no sha256 digest for {}",
+                    version, self, name
+                ))
+            })?;
+
+        Ok(Some(ReleaseArchive {
+            url: archive.browser_download_url.clone(),
+            sha256: parse_digest(&read(&self.client, &digest.browser_download_url)?, &name)?,
+        }))
+    }
+}
+
+/// A JSON or YAML file listing releases, read from a URL or a local path:
+///
+/// ```yaml
+/// releases:
+///   - v3.2.4
+///   - version: v3.2.4-acme.1
+///   - version: v3.3.0-rc.1
+///     prerelease: true
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexFile {
+    location: String,
+    mirror: Option<String>,
+    client: Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct Index {
+    releases: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum IndexEntry {
+    Tag(String),
+    Release {
+        version: String,
+        prerelease: Option<bool>,
+    },
+}
+
+impl IndexFile {
+    pub fn new(location: &str, client: Client) -> IndexFile {
+        IndexFile {
+            location: location.to_string(),
+            mirror: None,
+            client,
+        }
+    }
+
+    /// Download the archives of the listed releases from `mirror` instead of the Felloe mirror.
+    pub fn with_mirror(mut self, mirror: Option<&str>) -> IndexFile {
+        self.mirror = mirror.map(String::from);
+        self
+    }
+}
+
+impl fmt::Display for IndexFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "release index {}", self.location)
+    }
+}
+
+impl ReleaseSource for IndexFile {
+    fn releases(&self, count: usize) -> Result<Vec<Release>, failure::Error> {
+        let content = read(&self.client, &self.location)?;
+
+        // YAML is a superset of JSON, so one parser reads both
+        let index: Index = serde_yaml::from_str(&content).map_err(|e| {
+            failure::err_msg(format!("Invalid release index {}: {}", self.location, e))
+        })?;
+
+        let releases = index
+            .releases
+            .into_iter()
+            .map(|entry| match entry {
+                IndexEntry::Tag(tag) => Release::from_tag(&normalize_version(&tag)),
+                IndexEntry::Release {
+                    version,
+                    prerelease,
+                } => {
+                    let mut release = Release::from_tag(&normalize_version(&version));
+                    if let Some(prerelease) = prerelease {
+                        release.prerelease = prerelease;
+                    }
+                    release
+                }
+            })
+            .collect();
+
+        Ok(newest(releases, count))
+    }
+
+    fn archive(
+        &self,
+        release: &Release,
+        platform: &Platform,
+    ) -> Result<Option<ReleaseArchive>, failure::Error> {
+        mirror_archive(
+            &self.client,
+            self.mirror.as_deref(),
+            &release.tag_name,
+            platform,
+        )
+    }
+}
+
+/// Releases found in the file names of a mirror's directory listing, such as an nginx or Apache
+/// autoindex page, or in a local directory of archives. Archives are downloaded from the same
+/// directory.
+#[derive(Debug, Clone)]
+pub struct Directory {
+    location: String,
+    client: Client,
+}
+
+impl Directory {
+    pub fn new(location: &str, client: Client) -> Directory {
+        Directory {
+            location: location.to_string(),
+            client,
+        }
+    }
+}
+
+impl fmt::Display for Directory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "directory {}", self.location)
+    }
+}
+
+impl ReleaseSource for Directory {
+    fn releases(&self, count: usize) -> Result<Vec<Release>, failure::Error> {
+        let listing = match local_path(&self.location) {
+            Some(path) => path
+                .read_dir()?
+                .filter_map(Result::ok)
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => read(&self.client, &self.location)?,
+        };
+
+        let archive = Regex::new(
+            r"helm-(v[0-9]+\.[0-9]+\.[0-9]+(?:-[0-9A-Za-z.]+)?)-(?:darwin|linux|windows)-",
+        )?;
+
+        let mut seen = HashSet::new();
+        let releases = archive
+            .captures_iter(&listing)
+            .map(|captures| captures[1].to_string())
+            .filter(|tag| seen.insert(tag.clone()))
+            .map(|tag| Release::from_tag(&tag))
+            .collect();
+
+        Ok(newest(releases, count))
+    }
+
+    fn archive(
+        &self,
+        release: &Release,
+        platform: &Platform,
+    ) -> Result<Option<ReleaseArchive>, failure::Error> {
+        mirror_archive(
+            &self.client,
+            Some(&self.location),
+            &release.tag_name,
+            platform,
+        )
+    }
+
+    fn download(
+        &self,
+        client: &Client,
+        url: &str,
+        file_name: &str,
+        task: &Task,
+    ) -> Result<Vec<u8>, failure::Error> {
+        match local_path(url) {
+            Some(path) => {
+                info!("Copying {}", file_name);
+                Ok(fs::read(path)?)
+            }
+            None => download_with(client, url, task),
+        }
+    }
+}
+
+/// Tags of a repository in an OCI registry, such as one holding helm builds pushed with ORAS.
+/// Tags that are not versions are ignored. Archives are the layers of each tag's manifest, found
+/// by their `org.opencontainers.image.title` annotation.
+#[derive(Debug, Clone)]
+pub struct Oci {
+    registry: String,
+    repository: String,
+    client: Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Descriptor {
+    digest: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BearerToken {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl Oci {
+    /// Tags of `repository` in `registry`, a host such as `ghcr.io` or a URL such as
+    /// `http://localhost:5000`.
+    pub fn new(registry: &str, repository: &str, client: Client) -> Oci {
+        Oci {
+            registry: registry.trim_end_matches('/').to_string(),
+            repository: repository.to_string(),
+            client,
+        }
+    }
+
+    fn base_url(&self) -> String {
+        if self.registry.contains("://") {
+            self.registry.clone()
+        } else {
+            format!("https://{}", self.registry)
+        }
+    }
+
+    /// Send a request, fetching an anonymous bearer token first when the registry asks for one.
+    fn send(
+        &self,
+        url: &str,
+        accept: Option<&str>,
+        token: &mut Option<String>,
+    ) -> Result<Response, failure::Error> {
+        let request = |token: &Option<String>| -> RequestBuilder {
+            let mut request = self.client.get(url);
+            if let Some(accept) = accept {
+                request = request.header(ACCEPT, accept);
+            }
+            match token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+        };
+
+        let response = request(token).send()?;
+        if response.status() != StatusCode::UNAUTHORIZED || token.is_some() {
+            return Ok(response.error_for_status()?);
+        }
+
+        let challenge = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        *token = Some(self.fetch_token(&challenge)?);
+
+        Ok(request(token).send()?.error_for_status()?)
+    }
+
+    /// Answer a `Bearer realm="…",service="…",scope="…"` challenge.
+    fn fetch_token(&self, challenge: &str) -> Result<String, failure::Error> {
+        let params = Regex::new(r#"(\w+)="([^"]*)""#)?;
+        let param = |name: &str| {
+            params
+                .captures_iter(challenge)
+                .find(|captures| &captures[1] == name)
+                .map(|captures| captures[2].to_string())
+        };
+
+        let realm = param("realm").ok_or_else(|| {
+            failure::err_msg(format!(
+                "{} requires credentials felloe does not support",
+                self
+            ))
+        })?;
+
+        let mut query = Vec::new();
+        for name in &["service", "scope"] {
+            if let Some(value) = param(name) {
+                query.push((*name, value));
+            }
+        }
+
+        debug!("Requesting a token for {} from {}", self, realm);
+        let token: BearerToken = self
+            .client
+            .get(&realm)
+            .query(&query)
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        token
+            .token
+            .or(token.access_token)
+            .ok_or_else(|| failure::err_msg(format!("{} did not issue a token", realm)))
+    }
+}
+
+impl fmt::Display for Oci {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OCI repository {}/{}", self.registry, self.repository)
+    }
+}
+
+impl ReleaseSource for Oci {
+    fn releases(&self, count: usize) -> Result<Vec<Release>, failure::Error> {
+        let mut url = format!("{}/v2/{}/tags/list", self.base_url(), self.repository);
+        let mut token = None;
+        let mut tags = Vec::new();
+
+        loop {
+            let mut response = self.send(&url, None, &mut token)?;
+            let next = next_page(&response);
+
+            let list: TagList = response.json()?;
+            tags.extend(list.tags.unwrap_or_default());
+
+            match next {
+                Some(path) => url = format!("{}{}", self.base_url(), path),
+                None => break,
+            }
+        }
+
+        let releases = tags
+            .iter()
+            .filter(|tag| Version::parse(tag).is_some())
+            .map(|tag| Release::from_tag(&normalize_version(tag)))
+            .collect();
+
+        Ok(newest(releases, count))
+    }
+
+    fn archive(
+        &self,
+        release: &Release,
+        platform: &Platform,
+    ) -> Result<Option<ReleaseArchive>, failure::Error> {
+        let version = release.tag_name.as_str();
+
+        // Tags are listed with a `v` prefix even when the registry has them without one
+        let mut tags = vec![version];
+        if let Some(bare) = version.strip_prefix('v') {
+            tags.push(bare);
+        }
+
+        let mut token = None;
+        let mut manifest = None;
+        for tag in tags {
+            let url = format!(
+                "{}/v2/{}/manifests/{}",
+                self.base_url(),
+                self.repository,
+                tag
+            );
+            match self.send(&url, Some(OCI_MANIFEST), &mut token) {
+                Ok(mut response) => {
+                    manifest = Some(response.json::<Manifest>()?);
+                    break;
+                }
+                Err(e) => debug!("No manifest for {} in {}: {}", tag, self, e),
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| {
+            failure::err_msg(format!("helm {} is not available from {}", version, self))
+        })?;
+
+        let name = platform.archive_name(version);
+        let layer = manifest
+            .layers
+            .into_iter()
+            .find(|layer| layer.annotations.get(OCI_TITLE) == Some(&name))
+            .ok_or_else(|| {
+                failure::err_msg(format!("helm {} on {} has no {}", version, self, name))
+            })?;
+
+        let sha256 = layer
+            .digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| {
+                failure::err_msg(format!(
+                    "{} on {} is not a sha256 digest",
+                    layer.digest, self
+                ))
+            })?
+            .to_string();
+
+        Ok(Some(ReleaseArchive {
+            url: format!(
+                "{}/v2/{}/blobs/{}",
+                self.base_url(),
+                self.repository,
+                layer.digest
+            ),
+            sha256,
+        }))
+    }
+
+    fn download(
+        &self,
+        _client: &Client,
+        url: &str,
+        file_name: &str,
+        task: &Task,
+    ) -> Result<Vec<u8>, failure::Error> {
+        let response = self.send(url, None, &mut None)?;
+        task.start_download(file_name, response.content_length().unwrap_or(0));
+
+        info!("Downloading {}", file_name);
+        read_download(response, task)
+    }
+}
+
+const OCI_MANIFEST: &str = "application/vnd.oci.image.manifest.v1+json";
+
+const OCI_TITLE: &str = "org.opencontainers.image.title";
+
+/// The path of the next page from a `Link: </v2/…?last=…>; rel="next"` header.
+fn next_page(response: &Response) -> Option<String> {
+    let link = response.headers().get("link")?.to_str().ok()?;
+    if !link.contains("rel=\"next\"") {
+        return None;
+    }
+
+    let start = link.find('<')? + 1;
+    let end = link.find('>')?;
+    Some(link.get(start..end)?.to_string())
+}
+
+/// The archive of `version` for `platform` in `mirror`, with the digest published next to it the
+/// way `https://get.helm.sh` does, or `None` without a mirror.
+fn mirror_archive(
+    client: &Client,
+    mirror: Option<&str>,
+    version: &str,
+    platform: &Platform,
+) -> Result<Option<ReleaseArchive>, failure::Error> {
+    let mirror = match mirror {
+        Some(mirror) => mirror,
+        None => return Ok(None),
+    };
+
+    let name = platform.archive_name(version);
+    let url = format!("{}/{}", mirror.trim_end_matches('/'), name);
+    let sha256 = parse_digest(&read(client, &format!("{}.sha256", url))?, &name)?;
+
+    Ok(Some(ReleaseArchive { url, sha256 }))
+}
+
+/// The digest in a `.sha256` file, which may be followed by the file name.
+fn parse_digest(content: &str, name: &str) -> Result<String, failure::Error> {
+    content
+        .split_whitespace()
+        .next()
+        .map(String::from)
+        .ok_or_else(|| failure::err_msg(format!("No sha256 digest published for {}", name)))
+}
+
+/// Read a URL, or a local file for paths and `file://` URLs.
+fn read(client: &Client, location: &str) -> Result<String, failure::Error> {
+    match local_path(location) {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => Ok(client.get(location).send()?.error_for_status()?.text()?),
+    }
+}
+
+pub(crate) fn local_path(location: &str) -> Option<&Path> {
+    if let Some(path) = location.strip_prefix("file://") {
+        return Some(Path::new(path));
+    }
+
+    if location.starts_with("http://") || location.starts_with("https://") {
+        None
+    } else {
+        Some(Path::new(location))
+    }
+}
+
+/// The newest `count` of `releases`, oldest first.
+fn newest(mut releases: Vec<Release>, count: usize) -> Vec<Release> {
+    releases.sort_by(by_version);
+    let skip = releases.len().saturating_sub(count);
+    releases.split_off(skip)
+}
@@ -0,0 +1,127 @@
+use crate::config::{PickerConfig, PickerTheme};
+use crossterm::{
+    queue,
+    style::{Attribute, Color, SetAttribute, SetForegroundColor},
+};
+use std::{env, io::Write};
+
+/// How one kind of text in the picker is drawn.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    fn color(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            ..Style::default()
+        }
+    }
+
+    /// Queue the escape codes that switch to this style.
+    pub fn apply(&self, w: &mut impl Write) -> Result<(), failure::Error> {
+        if let Some(color) = self.color {
+            queue!(w, SetForegroundColor(color))?;
+        }
+        if self.bold {
+            queue!(w, SetAttribute(Attribute::Bold))?;
+        }
+        if self.reverse {
+            queue!(w, SetAttribute(Attribute::Reverse))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Styles and symbols of the interactive picker.
+#[derive(Debug)]
+pub struct Theme {
+    pub selected: Style,
+    pub remote: Style,
+    pub hints: Style,
+    /// Use plain ASCII markers and spelled out keys, for screen readers and limited fonts
+    pub ascii: bool,
+}
+
+impl Theme {
+    /// The configured theme and colors, without colors when `NO_COLOR` is set.
+    pub fn load(config: &PickerConfig) -> Result<Theme, failure::Error> {
+        let mut theme = match config.theme {
+            PickerTheme::Default => Theme {
+                selected: Style::color(Color::Blue),
+                remote: Style::color(Color::DarkGrey),
+                hints: Style::color(Color::DarkGrey),
+                ascii: false,
+            },
+            PickerTheme::HighContrast => Theme {
+                selected: Style {
+                    color: None,
+                    bold: true,
+                    reverse: true,
+                },
+                remote: Style::default(),
+                hints: Style::default(),
+                ascii: false,
+            },
+        };
+
+        let colors = &config.colors;
+        for (style, color) in [
+            (&mut theme.selected, &colors.selected),
+            (&mut theme.remote, &colors.remote),
+            (&mut theme.hints, &colors.hints),
+        ] {
+            if let Some(color) = color {
+                style.color = Some(parse_color(color)?);
+            }
+        }
+
+        if config.accessible {
+            theme.selected.bold = true;
+            theme.selected.reverse = true;
+            theme.ascii = true;
+        }
+
+        // https://no-color.org
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            for style in [&mut theme.selected, &mut theme.remote, &mut theme.hints] {
+                style.color = None;
+            }
+            theme.selected.reverse = true;
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse a color name such as `blue` or `dark-grey`.
+pub fn parse_color(name: &str) -> Result<Color, failure::Error> {
+    Ok(match name.to_lowercase().replace('_', "-").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "dark-red" => Color::DarkRed,
+        "green" => Color::Green,
+        "dark-green" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "dark-yellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "dark-blue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "dark-magenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "dark-cyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        "dark-grey" | "dark-gray" => Color::DarkGrey,
+        _ => {
+            return Err(failure::err_msg(format!(
+                "Unknown color {}. Expected a color such as blue, dark-grey or white",
+                name
+            )))
+        }
+    })
+}
@@ -0,0 +1,242 @@
+use crate::constants::*;
+use crate::felloe::Felloe;
+use crate::platform::Platform;
+use crate::project::normalize_version;
+use crate::release::is_helm2;
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// Details about the tiller started by felloe, stored next to its pid file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TillerState {
+    pub version: String,
+    pub listen: String,
+    // Tillers started before these were recorded ran with the `tiller start` defaults
+    #[serde(default = "default_storage")]
+    pub storage: String,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+}
+
+fn default_storage() -> String {
+    "secret".to_string()
+}
+
+fn default_namespace() -> String {
+    "kube-system".to_string()
+}
+
+fn get_tiller_dir(felloe: &Felloe) -> PathBuf {
+    felloe.home().join("tiller")
+}
+
+fn get_pid_path(felloe: &Felloe) -> PathBuf {
+    get_tiller_dir(felloe).join(TILLER_PID_FILE)
+}
+
+fn get_state_path(felloe: &Felloe) -> PathBuf {
+    get_tiller_dir(felloe).join(TILLER_STATE_FILE)
+}
+
+pub fn get_log_path(felloe: &Felloe) -> PathBuf {
+    get_tiller_dir(felloe).join(TILLER_LOG_FILE)
+}
+
+/// The pid and state of the running tiller. Stale pid files are cleaned up.
+pub fn running(felloe: &Felloe) -> Result<Option<(u32, TillerState)>, failure::Error> {
+    let pid_path = get_pid_path(felloe);
+    if !pid_path.exists() {
+        return Ok(None);
+    }
+
+    let pid: u32 = fs::read_to_string(&pid_path)?.trim().parse()?;
+    if !is_alive(pid) {
+        debug!("Removing stale tiller pid file for {}", pid);
+        fs::remove_file(&pid_path)?;
+        return Ok(None);
+    }
+
+    let state = serde_json::from_str(&fs::read_to_string(get_state_path(felloe))?)?;
+    Ok(Some((pid, state)))
+}
+
+/// The `HELM_HOST` that helm `version` should use to reach the local tiller, if any.
+pub fn helm_host(felloe: &Felloe, version: &str) -> Option<String> {
+    if !is_helm2(version) {
+        return None;
+    }
+
+    match running(felloe) {
+        Ok(Some((_, state))) if state.version == version => Some(state.listen),
+        _ => None,
+    }
+}
+
+pub fn start(
+    felloe: &Felloe,
+    version: Option<String>,
+    listen: &str,
+    storage: &str,
+    namespace: &str,
+) -> Result<(), failure::Error> {
+    let version = felloe.resolve(version.as_deref())?;
+    if !is_helm2(&version) {
+        return Err(failure::err_msg(format!(
+            "helm {} does not use tiller",
+            version
+        )));
+    }
+
+    if let Some((pid, state)) = running(felloe)? {
+        return Err(failure::err_msg(format!(
+            "tiller {} is already running (pid {})",
+            state.version, pid
+        )));
+    }
+
+    let tiller_path = felloe
+        .install_path(&version, &Platform::current())
+        .join(TILLER_BIN_NAME);
+    if !tiller_path.exists() {
+        return Err(failure::err_msg(format!(
+            "A tiller binary is not installed for {}",
+            version
+        )));
+    }
+
+    fs::create_dir_all(get_tiller_dir(felloe))?;
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_log_path(felloe))?;
+
+    info!("Starting {} listening on {}", tiller_path.display(), listen);
+    let child = Command::new(&tiller_path)
+        .arg(format!("--listen={}", listen))
+        .arg(format!("--storage={}", storage))
+        .env("TILLER_NAMESPACE", namespace)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .spawn()?;
+
+    let state = TillerState {
+        version: version.clone(),
+        listen: listen.to_string(),
+        storage: storage.to_string(),
+        namespace: namespace.to_string(),
+    };
+    fs::write(get_state_path(felloe), serde_json::to_string(&state)?)?;
+    fs::write(get_pid_path(felloe), child.id().to_string())?;
+
+    println!(
+        "Started tiller {} (pid {}) listening on {}",
+        version,
+        child.id(),
+        listen
+    );
+    println!("Logs are written to {}", get_log_path(felloe).display());
+    Ok(())
+}
+
+pub fn stop(felloe: &Felloe, version: Option<String>) -> Result<(), failure::Error> {
+    let (pid, state) = match running_version(felloe, version)? {
+        Some(running) => running,
+        None => return Ok(()),
+    };
+
+    info!("Stopping tiller {} (pid {})", state.version, pid);
+    kill(pid)?;
+    fs::remove_file(get_pid_path(felloe))?;
+
+    println!("Stopped tiller {}", state.version);
+    Ok(())
+}
+
+pub fn status(felloe: &Felloe, version: Option<String>) -> Result<(), failure::Error> {
+    if let Some((pid, state)) = running_version(felloe, version)? {
+        println!(
+            "tiller {} is running (pid {}) on {}",
+            state.version, pid, state.listen
+        );
+        println!("Logs: {}", get_log_path(felloe).display());
+    }
+
+    Ok(())
+}
+
+/// The running tiller, reporting when it is not running or is a different version.
+fn running_version(
+    felloe: &Felloe,
+    version: Option<String>,
+) -> Result<Option<(u32, TillerState)>, failure::Error> {
+    let version = version.map(|v| normalize_version(&v));
+
+    match running(felloe)? {
+        Some((_, state)) if version.is_some() && version.as_ref() != Some(&state.version) => {
+            println!(
+                "tiller {} is not running (running: {})",
+                version.unwrap(),
+                state.version
+            );
+            Ok(None)
+        }
+        Some(running) => Ok(Some(running)),
+        None => {
+            println!("tiller is not running");
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn is_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn kill(pid: u32) -> Result<(), failure::Error> {
+    let status = Command::new("kill").arg(pid.to_string()).status()?;
+    if !status.success() {
+        return Err(failure::err_msg(format!(
+            "Unable to stop tiller (pid {})",
+            pid
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn kill(pid: u32) -> Result<(), failure::Error> {
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .status()?;
+    if !status.success() {
+        return Err(failure::err_msg(format!(
+            "Unable to stop tiller (pid {})",
+            pid
+        )));
+    }
+
+    Ok(())
+}
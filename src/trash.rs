@@ -0,0 +1,144 @@
+use crate::commands::dir_size;
+use crate::config::Config;
+use crate::constants::TRASH_DIR_NAME;
+use crate::felloe::Felloe;
+use crate::metadata::{days_ago, now};
+use crate::project::normalize_version;
+use crate::prune::parse_age;
+use indicatif::HumanBytes;
+use log::*;
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// A removed version waiting in the trash, stored as `<version>@<unix time removed>`.
+#[derive(Debug)]
+pub struct TrashedVersion {
+    pub version: String,
+    pub removed_at: u64,
+    pub path: PathBuf,
+}
+
+pub fn get_trash_path(felloe: &Felloe) -> PathBuf {
+    felloe.home().join(TRASH_DIR_NAME)
+}
+
+/// Every version in the trash, most recently removed first.
+pub fn trashed(felloe: &Felloe) -> Result<Vec<TrashedVersion>, failure::Error> {
+    let root = get_trash_path(felloe);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut trashed = Vec::new();
+    for entry in root.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if let Some((version, removed_at)) = name.rsplit_once('@') {
+            if let Ok(removed_at) = removed_at.parse() {
+                trashed.push(TrashedVersion {
+                    version: version.to_string(),
+                    removed_at,
+                    path: entry.path(),
+                });
+            }
+        }
+    }
+
+    trashed.sort_by_key(|trashed| Reverse(trashed.removed_at));
+    Ok(trashed)
+}
+
+/// Delete the downloaded `version`, or move it to the trash when the trash is enabled.
+pub fn discard(felloe: &Felloe, version: &str, path: &Path) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+
+    if !config.trash.enabled {
+        fs::remove_dir_all(path)?;
+        return Ok(());
+    }
+
+    let root = get_trash_path(felloe);
+    fs::create_dir_all(&root)?;
+
+    let target = root.join(format!("{}@{}", version, now()));
+    info!("Moving {:?} to {:?}", path, target);
+    fs::rename(path, target)?;
+
+    purge(felloe, &config)
+}
+
+/// Delete versions that have been in the trash longer than `trash.keep-for`.
+pub fn purge(felloe: &Felloe, config: &Config) -> Result<(), failure::Error> {
+    let keep_for = parse_age(&config.trash.keep_for)?;
+
+    for trashed in trashed(felloe)? {
+        if now().saturating_sub(trashed.removed_at) > keep_for.as_secs() {
+            info!("Deleting helm {} from the trash", trashed.version);
+            fs::remove_dir_all(&trashed.path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the most recently removed `version` back from the trash, or list the trash when no
+/// version is given.
+pub fn restore(felloe: &Felloe, version: Option<String>) -> Result<(), failure::Error> {
+    let config = felloe.config()?;
+    purge(felloe, &config)?;
+
+    let version = match version {
+        Some(version) => normalize_version(&version),
+        None => return list(felloe, &config),
+    };
+
+    let trashed = trashed(felloe)?
+        .into_iter()
+        .find(|trashed| trashed.version == version)
+        .ok_or_else(|| failure::err_msg(format!("helm {} is not in the trash", version)))?;
+
+    let path = felloe.cache_path(&version);
+    if path.exists() {
+        return Err(failure::err_msg(format!(
+            "helm {} is already installed",
+            version
+        )));
+    }
+
+    fs::rename(&trashed.path, &path)?;
+    println!("Restored helm {}", version);
+
+    Ok(())
+}
+
+fn list(felloe: &Felloe, config: &Config) -> Result<(), failure::Error> {
+    let trashed = trashed(felloe)?;
+
+    if trashed.is_empty() {
+        println!("The trash is empty");
+        return Ok(());
+    }
+
+    for trashed in &trashed {
+        let removed = UNIX_EPOCH + Duration::from_secs(trashed.removed_at);
+
+        println!(
+            "{:<14} {:>10}  removed {}",
+            trashed.version,
+            HumanBytes(dir_size(&trashed.path)).to_string(),
+            days_ago(Some(removed))
+        );
+    }
+
+    println!(
+        "Versions are deleted from the trash {} after their removal",
+        config.trash.keep_for
+    );
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use crate::commands::{after_activate, fetch_many, report_fetched};
+use crate::constants::{DEFAULT_DOWNLOAD_JOBS, RELEASE_INDEX_SIZE};
+use crate::felloe::Felloe;
+use crate::platform::Platform;
+use crate::release::{is_helm2, Version};
+use log::*;
+use std::{fmt, time::Instant};
+
+/// How far `felloe upgrade` may move from the active version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeScope {
+    /// Newer patch releases of the active minor version
+    Patch,
+    /// Newer minor and patch releases of the active major version
+    Minor,
+    /// Any newer release
+    Major,
+}
+
+impl UpgradeScope {
+    fn allows(self, current: &Version, candidate: &Version) -> bool {
+        candidate > current
+            && match self {
+                UpgradeScope::Patch => {
+                    candidate.major == current.major && candidate.minor == current.minor
+                }
+                UpgradeScope::Minor => candidate.major == current.major,
+                UpgradeScope::Major => true,
+            }
+    }
+}
+
+impl fmt::Display for UpgradeScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UpgradeScope::Patch => write!(f, "patch"),
+            UpgradeScope::Minor => write!(f, "minor"),
+            UpgradeScope::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// The newest of `releases` that `scope` allows moving to from `current`, if any.
+pub fn newest_in<'a>(
+    current: &Version,
+    scope: UpgradeScope,
+    releases: &'a [Version],
+) -> Option<&'a Version> {
+    releases.iter().filter(|v| scope.allows(current, v)).max()
+}
+
+/// The newest release `scope` allows moving to from `current`, if any.
+pub fn newest(
+    felloe: &Felloe,
+    current: &str,
+    scope: UpgradeScope,
+) -> Result<Option<String>, failure::Error> {
+    let current = Version::parse(current)
+        .ok_or_else(|| failure::err_msg(format!("Unable to parse helm version {}", current)))?;
+
+    let releases: Vec<Version> = felloe
+        .fetch_releases(RELEASE_INDEX_SIZE, false)?
+        .0
+        .iter()
+        .filter_map(|r| Version::parse(&r.tag_name))
+        .collect();
+
+    Ok(newest_in(&current, scope, &releases).map(Version::to_string))
+}
+
+/// Install and activate the newest release `scope` allows, optionally removing the version it
+/// replaces.
+pub fn upgrade(
+    felloe: &Felloe,
+    scope: UpgradeScope,
+    dry_run: bool,
+    prune: bool,
+) -> Result<(), failure::Error> {
+    let active = felloe
+        .active()?
+        .ok_or_else(|| failure::err_msg("An active version is not set"))?;
+
+    let target = match newest(felloe, &active, scope)? {
+        Some(target) => target,
+        None => {
+            println!("helm {} is already the newest {} release", active, scope);
+            return Ok(());
+        }
+    };
+
+    if is_helm2(&active) && !is_helm2(&target) {
+        warn!(
+            "helm {} no longer uses tiller. Run `felloe migrate {} {}` to convert your releases",
+            target, active, target
+        );
+    }
+
+    if dry_run {
+        println!("Would upgrade helm {} to {}", active, target);
+        if prune {
+            println!("Would remove helm {}", active);
+        }
+        return Ok(());
+    }
+
+    info!("Upgrading helm {} to {}", active, target);
+    let started = Instant::now();
+    let results = fetch_many(
+        felloe,
+        vec![target.clone()],
+        &Platform::current(),
+        DEFAULT_DOWNLOAD_JOBS,
+    )?;
+    report_fetched(&results, &format!("helm {}", target), started)?;
+
+    let activated = felloe.activate(&target)?;
+    println!("Activated helm {}", target);
+    after_activate(felloe, activated.previous, &target);
+
+    if prune {
+        felloe.remove(std::slice::from_ref(&active), false)?;
+        println!("Removed helm {}", active);
+    }
+
+    Ok(())
+}